@@ -0,0 +1,91 @@
+use tui::style::Color;
+
+use crate::board::Board;
+use crate::game::{Action, CellState, Game, MoveKind};
+use crate::layout::{self, Layout};
+use crate::solver;
+use crate::topology;
+
+// what a reveal or chord was worth, judged from what the board showed right before it
+#[derive(Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Forced,      // the numbers proved every cell it opened safe, the first click counts too
+    Lucky,       // a guess, but nothing on the board was proven safe
+    Unnecessary, // a guess while a proven safe cell was waiting somewhere else
+    Fatal,       // opened a mine
+}
+
+impl Verdict {
+    pub fn color(self) -> Color {
+        match self {
+            Verdict::Forced => Color::Green,
+            Verdict::Lucky => Color::Yellow,
+            Verdict::Unnecessary => Color::Magenta,
+            Verdict::Fatal => Color::Red,
+        }
+    }
+}
+
+pub struct Analysis {
+    pub verdicts: Vec<Option<Verdict>>, // indexed like the board, on the cell each move was made at
+    counts: [usize; 4],
+}
+
+impl Analysis {
+    pub fn summary(&self) -> String {
+        let [forced, lucky, unnecessary, fatal] = self.counts;
+        format!("Forced {}, lucky {}, unnecessary {}, fatal {} (green, yellow, magenta, red)", forced, lucky, unnecessary, fatal)
+    }
+}
+
+// plays the moves again on a copy of the board as it started, asking the solver before each one. versus games
+// follow their own rules and games without moves have nothing to look at
+pub fn analyze(game: &Game) -> Option<Analysis> {
+    if game.versus.is_some() || game.moves.is_empty() { return None; }
+
+    let board = game.board();
+    let layout = match game.layout() {
+        Some(layout) => layout.clone(), // a loaded board starts with whatever its file had open
+        None => Layout { states: None, ..Layout::parse(&layout::export(board, true)).ok()? },
+    };
+    let topology = topology::build(board.width(), board.height(), board.topology().is_hex(), board.topology().wraps(), board.topology().neighborhood());
+    let mut replay = Game::from_layout(topology, &layout).ok()?;
+    if let Some(lives) = game.lives { replay = replay.with_lives(lives.total); } // so the moves after a blown up mine are judged too
+
+    let mut analysis = Analysis { verdicts: vec![None; board.width() * board.height()], counts: [0; 4] };
+    for step in &game.moves {
+        let opened = match step.kind {
+            MoveKind::Flag => Vec::new(),
+            MoveKind::Reveal => vec![(step.x, step.y)],
+            MoveKind::Chord => chorded(replay.board(), step.x, step.y),
+        };
+        if !opened.is_empty() {
+            let verdict = judge(replay.board(), &opened);
+            analysis.verdicts[board.index(step.x, step.y)] = Some(verdict);
+            analysis.counts[verdict as usize] += 1;
+        }
+
+        let action = if step.kind == MoveKind::Flag { Action::Flag } else { Action::Reveal };
+        replay.apply_at(step.x, step.y, action);
+    }
+    Some(analysis)
+}
+
+fn judge(board: &Board, opened: &[(usize, usize)]) -> Verdict {
+    if opened.iter().any(|&(x, y)| board.is_mine(x, y)) { return Verdict::Fatal; }
+
+    let cells = (0..board.height()).flat_map(|y| (0..board.width()).map(move |x| (x, y)));
+    if cells.clone().all(|(x, y)| board.state(x, y) != CellState::REVEALED) { return Verdict::Forced; } // the first click is always safe
+
+    let known = solver::prove(board);
+    if opened.iter().all(|&(x, y)| known[board.index(x, y)] == Some(0)) { return Verdict::Forced; }
+
+    let safe_elsewhere = cells.filter(|&(x, y)| board.state(x, y) == CellState::HIDDEN).any(|(x, y)| known[board.index(x, y)] == Some(0));
+    if safe_elsewhere { Verdict::Unnecessary } else { Verdict::Lucky }
+}
+
+// the cells a chord on this number opens, none if its flags do not add up yet
+fn chorded(board: &Board, x: usize, y: usize) -> Vec<(usize, usize)> {
+    if board.state(x, y) != CellState::REVEALED || board.marked_around(x, y) != board.mines_seen(x, y) as usize { return Vec::new(); }
+    board.neighbors(x, y).filter(|&(nx, ny)| board.state(nx, ny) == CellState::HIDDEN).collect()
+}
@@ -0,0 +1,156 @@
+use std::{hint::black_box, thread, time::{Duration, Instant}};
+
+use crate::board::Board;
+use crate::game::{CellState, Difficulty};
+use crate::helpers::{incriment_neighbors, reveal_safe_neighbors};
+use crate::topology::SquareTopology;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SIZES: [(usize, usize); 4] = [(30, 16), (250, 250), (1000, 1000), (2000, 2000)];
+const SEED: u64 = 0x5eed;
+const LEGACY_STACK_SIZE: usize = 2 << 30; // the old flood fill recurses once per cell
+
+// the original Vec<Vec<Cell>> layout with the -9 mine sentinel, kept only to compare against
+struct LegacyCell {
+    mines_seen: i8,
+    cell_state: CellState,
+}
+
+pub fn run() {
+    println!("{:>11} | {:>14} {:>14} | {:>14} {:>14}", "board", "generate old", "generate new", "flood old", "flood new");
+
+    for (width, height) in SIZES {
+        let generate_old = time(|| { black_box(legacy_generate(width, height)); });
+        let generate_new = time(|| { black_box(generate(width, height)); });
+
+        // flood an empty board from the center, the worst case for both layouts
+        let flood_old = thread::Builder::new()
+            .stack_size(LEGACY_STACK_SIZE)
+            .spawn(move || {
+                let mut grid = legacy_grid(width, height);
+                time(|| legacy_reveal_safe_neighbors(width / 2, height / 2, &mut grid))
+            })
+            .expect("failed to spawn legacy benchmark thread")
+            .join()
+            .expect("legacy flood fill panicked");
+        let flood_new = {
+            let mut board = Board::new(Box::new(SquareTopology::new(width, height, false)), 1);
+            time(|| { black_box(reveal_safe_neighbors(width / 2, height / 2, &mut board)); })
+        };
+
+        println!(
+            "{:>11} | {:>14} {:>14} | {:>14} {:>14}",
+            format!("{}x{}", width, height),
+            format_duration(generate_old),
+            format_duration(generate_new),
+            format_duration(flood_old),
+            format_duration(flood_new),
+        );
+    }
+}
+
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    Instant::now().duration_since(start)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn mine_count(width: usize, height: usize) -> usize {
+    width * height * Difficulty::MEDIUM as usize / 100
+}
+
+fn generate(width: usize, height: usize) -> Board {
+    let mut board = Board::new(Box::new(SquareTopology::new(width, height, false)), 1);
+    let mut rng = StdRng::seed_from_u64(SEED);
+
+    let mut mines_placed = 0;
+    while mines_placed < mine_count(width, height) {
+        let x = rng.gen_range(0..width);
+        let y = rng.gen_range(0..height);
+        if board.add_mine(x, y) {
+            incriment_neighbors(x, y, &mut board);
+            mines_placed += 1;
+        }
+    }
+
+    board
+}
+
+fn legacy_grid(width: usize, height: usize) -> Vec<Vec<LegacyCell>> {
+    let mut grid = Vec::with_capacity(height);
+    for _ in 0..height {
+        let mut row = Vec::with_capacity(width);
+        for _ in 0..width {
+            row.push(LegacyCell { mines_seen: 0, cell_state: CellState::HIDDEN });
+        }
+        grid.push(row);
+    }
+    grid
+}
+
+fn legacy_generate(width: usize, height: usize) -> Vec<Vec<LegacyCell>> {
+    let mut grid = legacy_grid(width, height);
+    let mut rng = StdRng::seed_from_u64(SEED);
+
+    let mut mines_placed = 0;
+    while mines_placed < mine_count(width, height) {
+        let x = rng.gen_range(0..width);
+        let y = rng.gen_range(0..height);
+        if grid[y][x].mines_seen >= 0 {
+            grid[y][x].mines_seen = -9;
+            legacy_incriment_neighbors(x, y, &mut grid);
+            mines_placed += 1;
+        }
+    }
+
+    grid
+}
+
+fn legacy_incriment_neighbors(x: usize, y: usize, grid: &mut [Vec<LegacyCell>]) {
+    let max_column = grid.len() as i32;
+    let max_row = grid[0].len() as i32;
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 { continue; }
+
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+
+            if nx >= 0 && nx < max_row && ny >= 0 && ny < max_column {
+                let neighbor = &mut grid[ny as usize][nx as usize];
+                if neighbor.mines_seen < 0 { continue; }
+                neighbor.mines_seen += 1;
+            }
+        }
+    }
+}
+
+fn legacy_reveal_safe_neighbors(x: usize, y: usize, grid: &mut [Vec<LegacyCell>]) {
+    let max_column = grid.len() as i32;
+    let max_row = grid[0].len() as i32;
+
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 { continue; }
+
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+
+            if nx >= 0 && nx < max_row && ny >= 0 && ny < max_column {
+                let neighbor = &mut grid[ny as usize][nx as usize];
+
+                if neighbor.cell_state == CellState::HIDDEN {
+                    neighbor.cell_state = CellState::REVEALED;
+                    if neighbor.mines_seen == 0 {
+                        legacy_reveal_safe_neighbors(nx as usize, ny as usize, grid);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use crate::game::CellState;
+use crate::topology::{Neighbors, Stencil, Topology};
+
+const CHUNK_SIDE: usize = 16;
+
+// a single snapshot of one cell, the board itself stores each field in its own buffer
+#[derive(Clone, Copy)]
+pub struct Cell {
+    pub is_mine: bool,
+    pub mines: u8, // how many mines are stacked in this cell, only ever above 1 with multi mine cells
+    pub mines_seen: u8,
+    pub flags: u8,
+    pub cell_state: CellState,
+}
+
+pub struct Board {
+    topology: Box<dyn Topology>,
+    stencil: Stencil, // the topology's neighbors, kept here so walking them needs no dynamic call
+    width: usize,
+    height: usize,
+    max_mines: u8,
+    mine_bits: Vec<u64>, // one bit per cell, row major
+    stacked_mines: Vec<u8>, // per cell mine counts, left empty unless a cell can hold more than one mine
+    flags: Vec<u8>, // per cell flag counts, left empty for the same reason
+    mines_seen: Vec<u8>, // cached neighbor counts, only meaningful for non mine cells
+    states: Vec<CellState>,
+    chunks: Option<Box<Chunks>>, // only endless boards have these and they leave the buffers above empty, boxed so the flat board accessors stay as fast as before
+}
+
+// the cells of an endless board, far too many to hold at once. only what the player did to a cell is kept,
+// CHUNK_SIDE by CHUNK_SIDE cells at a time as play reaches them. mines are worked out from the seed and the
+// cell's position whenever something asks, so the same seed always grows the same world whatever is explored first
+struct Chunks {
+    density: usize, // percent of cells that are mines, as with Difficulty
+    mines: Option<(u64, Vec<(usize, usize)>)>, // the seed and the cells kept clear around the first click, nothing is a mine before it
+    states: HashMap<(usize, usize), Vec<CellState>>, // row major within the chunk
+}
+
+impl Board {
+    pub fn new(topology: Box<dyn Topology>, max_mines: u8) -> Self {
+        let cells = topology.cells();
+        let stacked = if max_mines > 1 { cells } else { 0 };
+        Board {
+            width: topology.width(),
+            height: topology.height(),
+            stencil: topology.stencil(),
+            topology,
+            max_mines,
+            mine_bits: vec![0; cells.div_ceil(64)],
+            stacked_mines: vec![0; stacked],
+            flags: vec![0; stacked],
+            mines_seen: vec![0; cells],
+            states: vec![CellState::HIDDEN; cells],
+            chunks: None,
+        }
+    }
+
+    // a board too big to store, see Chunks
+    pub fn endless(topology: Box<dyn Topology>, density: usize) -> Self {
+        Board {
+            width: topology.width(),
+            height: topology.height(),
+            stencil: topology.stencil(),
+            topology,
+            max_mines: 1,
+            mine_bits: Vec::new(),
+            stacked_mines: Vec::new(),
+            flags: Vec::new(),
+            mines_seen: Vec::new(),
+            states: Vec::new(),
+            chunks: Some(Box::new(Chunks { density, mines: None, states: HashMap::new() })),
+        }
+    }
+
+    pub fn is_endless(&self) -> bool { self.chunks.is_some() }
+
+    // stands in for placing mines on an endless board, every cell but the safe ones may now turn out to be one
+    pub fn seed_mines(&mut self, seed: u64, safe: Vec<(usize, usize)>) {
+        if let Some(chunks) = &mut self.chunks { chunks.mines = Some((seed, safe)); }
+    }
+
+    // has to happen before any mines are placed
+    pub fn set_max_mines(&mut self, max_mines: u8) {
+        let stacked = if max_mines > 1 { self.states.len() } else { 0 };
+        self.max_mines = max_mines;
+        self.stacked_mines = vec![0; stacked];
+        self.flags = vec![0; stacked];
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+    pub fn max_mines(&self) -> u8 { self.max_mines }
+    pub fn topology(&self) -> &dyn Topology { self.topology.as_ref() }
+
+    #[inline]
+    pub fn neighbors(&self, x: usize, y: usize) -> Neighbors { self.stencil.at(x, y) }
+
+    pub fn index(&self, x: usize, y: usize) -> usize { y * self.width + x }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        if self.chunks.is_some() {
+            let (is_mine, state) = (self.is_mine(x, y), self.state(x, y));
+            return Cell { is_mine, mines: is_mine as u8, mines_seen: self.mines_seen(x, y), flags: (state == CellState::FLAGGED) as u8, cell_state: state };
+        }
+
+        let i = self.index(x, y);
+        Cell {
+            is_mine: self.is_mine_at(i),
+            mines: self.mines_at(i),
+            mines_seen: self.mines_seen[i],
+            flags: self.flags_at(i),
+            cell_state: self.states[i],
+        }
+    }
+
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        match &self.chunks {
+            Some(chunks) => chunks.is_mine(x, y),
+            None => self.is_mine_at(self.index(x, y)),
+        }
+    }
+
+    pub fn mines_seen(&self, x: usize, y: usize) -> u8 {
+        match &self.chunks {
+            Some(chunks) => chunks.mines_seen(self.neighbors(x, y)), // nothing to cache it in
+            None => self.mines_seen[self.index(x, y)],
+        }
+    }
+
+    pub fn state(&self, x: usize, y: usize) -> CellState {
+        match &self.chunks {
+            Some(chunks) => chunks.state(x, y),
+            None => self.states[self.index(x, y)],
+        }
+    }
+
+    pub fn set_state(&mut self, x: usize, y: usize, state: CellState) {
+        let i = self.index(x, y);
+        match &mut self.chunks {
+            Some(chunks) => chunks.set_state(x, y, state),
+            None => self.states[i] = state,
+        }
+    }
+
+    // only tracked when cells can hold more than one mine, a classic flag is just the FLAGGED state
+    pub fn set_flags(&mut self, x: usize, y: usize, flags: u8) {
+        let i = self.index(x, y);
+        if !self.flags.is_empty() { self.flags[i] = flags; }
+    }
+
+    // drops one more mine into a cell, returns false when the cell is already full
+    pub fn add_mine(&mut self, x: usize, y: usize) -> bool {
+        let i = self.index(x, y);
+        if self.mines_at(i) >= self.max_mines { return false; }
+
+        self.mine_bits[i / 64] |= 1 << (i % 64);
+        if !self.stacked_mines.is_empty() { self.stacked_mines[i] += 1; }
+        true
+    }
+
+    // cells holding at least one mine, every other cell has to be revealed to win
+    pub fn mine_cells(&self) -> usize { self.mine_bits.iter().map(|bits| bits.count_ones() as usize).sum() }
+
+    // the mines around a cell the player already knows about, flagged or blown up
+    pub fn marked_around(&self, x: usize, y: usize) -> usize {
+        self.neighbors(x, y).map(|(nx, ny)| {
+            let cell = self.cell(nx, ny);
+            if cell.cell_state == CellState::REVEALED && cell.is_mine { cell.mines as usize } else { cell.flags as usize }
+        }).sum()
+    }
+
+    pub fn increment_seen(&mut self, x: usize, y: usize) {
+        let i = self.index(x, y);
+        self.mines_seen[i] += 1;
+    }
+
+    // the minimum number of clicks needed to clear the board: one per opening plus one per
+    // numbered cell that no opening would uncover
+    pub fn three_bv(&self) -> usize { self.three_bv_progress().1 }
+
+    // how many of those clicks are already done, an opening counts once any of its empty cells is revealed
+    pub fn three_bv_progress(&self) -> (usize, usize) {
+        let mut covered = vec![false; self.states.len()];
+        let mut clicks = 0;
+        let mut solved = 0;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                if covered[i] || self.is_mine_at(i) || self.mines_seen[i] != 0 { continue; }
+
+                clicks += 1; // a new opening, flood it so the rest of it is not counted again
+                covered[i] = true;
+                let mut opened = self.states[i] == CellState::REVEALED;
+                let mut stack = vec![(x, y)];
+                while let Some((x, y)) = stack.pop() {
+                    for (nx, ny) in self.neighbors(x, y) {
+                        let n = self.index(nx, ny);
+                        if covered[n] || self.is_mine_at(n) { continue; }
+                        covered[n] = true;
+                        if self.mines_seen[n] == 0 {
+                            opened |= self.states[n] == CellState::REVEALED;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+                if opened { solved += 1; }
+            }
+        }
+
+        for (i, &covered) in covered.iter().enumerate() {
+            if covered || self.is_mine_at(i) { continue; }
+            clicks += 1;
+            if self.states[i] == CellState::REVEALED { solved += 1; }
+        }
+
+        (solved, clicks)
+    }
+
+    fn is_mine_at(&self, i: usize) -> bool { self.mine_bits[i / 64] & (1 << (i % 64)) != 0 }
+
+    fn mines_at(&self, i: usize) -> u8 {
+        if self.stacked_mines.is_empty() { self.is_mine_at(i) as u8 }
+        else { self.stacked_mines[i] }
+    }
+
+    fn flags_at(&self, i: usize) -> u8 {
+        if !self.flags.is_empty() { self.flags[i] }
+        else { (self.states[i] == CellState::FLAGGED) as u8 }
+    }
+}
+
+// kept out of line like Neighbors::near_edge, so the flat board accessors that branch to them stay small
+impl Chunks {
+    #[inline(never)]
+    fn is_mine(&self, x: usize, y: usize) -> bool {
+        let Some((seed, safe)) = &self.mines else { return false };
+        !safe.contains(&(x, y)) && roll(*seed, x, y) % 100 < self.density as u64
+    }
+
+    #[inline(never)]
+    fn mines_seen(&self, neighbors: Neighbors) -> u8 { neighbors.filter(|&(x, y)| self.is_mine(x, y)).count() as u8 }
+
+    #[inline(never)]
+    fn state(&self, x: usize, y: usize) -> CellState {
+        let chunk = self.states.get(&(x / CHUNK_SIDE, y / CHUNK_SIDE));
+        chunk.map_or(CellState::HIDDEN, |chunk| chunk[(y % CHUNK_SIDE) * CHUNK_SIDE + x % CHUNK_SIDE])
+    }
+
+    #[inline(never)]
+    fn set_state(&mut self, x: usize, y: usize, state: CellState) {
+        let chunk = self.states.entry((x / CHUNK_SIDE, y / CHUNK_SIDE)).or_insert_with(|| vec![CellState::HIDDEN; CHUNK_SIDE * CHUNK_SIDE]);
+        chunk[(y % CHUNK_SIDE) * CHUNK_SIDE + x % CHUNK_SIDE] = state;
+    }
+}
+
+// a number that only the seed and the cell decide. like helpers::seeded_rng it must never change, a seed would
+// grow a different world, so it is spelled out here: the splitmix64 finalizer over the seed mixed with both coordinates
+fn roll(seed: u64, x: usize, y: usize) -> u64 {
+    let mut z = seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
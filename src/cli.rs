@@ -0,0 +1,266 @@
+use clap::{ArgAction, Args, Parser, Subcommand};
+
+use crate::drill::Grade;
+use crate::game::Difficulty;
+use crate::preset::{self, Preset};
+use crate::share::Challenge;
+use crate::status::{self, StatusBar};
+use crate::topology::Neighborhood;
+use crate::tutorial;
+
+pub const MAX_BOARD_SIDE: usize = 1000; // far past anything a terminal can show, but still quick to generate
+
+// help text lives in the attributes, clap would otherwise pick it up from doc comments
+#[derive(Parser)]
+#[command(
+    name = "termsweeper",
+    version,
+    about = "A terminal-based Minesweeper game",
+    after_help = "Run without a command to play, the play options work on their own too.",
+    disable_help_flag = true, // -h is the board height
+    args_conflicts_with_subcommands = true,
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub play: PlayArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Play a game, the same as running without a command", disable_help_flag = true)]
+    Play(PlayArgs),
+    #[command(about = "Show your game history, best times and daily streak")]
+    Stats(HelpArgs),
+    #[command(about = "Watch a RAW Minesweeper (.rawvf) replay")]
+    Replay(ReplayArgs),
+    #[command(about = "Time board generation and flood fill against the old grid layout")]
+    Bench(HelpArgs),
+    #[command(about = "Print everything the solver can prove about a board file")]
+    Solve(SolveArgs),
+    #[command(about = "Host games for remote terminals, anyone can connect with telnet")]
+    Serve(ServeArgs),
+    #[command(about = "Learn the common patterns on hand made boards, one lesson at a time")]
+    Tutorial(TutorialArgs),
+    #[command(about = "Solve generated puzzles that have exactly one proven move, with your accuracy kept")]
+    Drill(DrillArgs),
+    #[command(about = "Sweep a board without edges that grows as you scroll, mines cost points instead of the game", alias = "zen")]
+    Endless(EndlessArgs),
+}
+
+#[derive(Args)]
+pub struct PlayArgs {
+    #[arg(short, long, value_parser = board_side, help = "Grid width (default: fits the terminal)")]
+    pub width: Option<usize>,
+
+    #[arg(short, long, value_parser = board_side, help = "Grid height (default: fits the terminal)")]
+    pub height: Option<usize>,
+
+    #[arg(short, long, value_name = "LEVEL", value_parser = difficulty, help = "Share of cells that are mines: easy, medium, hard [default: medium]")]
+    pub difficulty: Option<Difficulty>,
+
+    #[arg(short, long, value_name = "RULE", value_parser = neighborhood, default_value = "moore", help = "Which cells a number counts on square grids: moore, knight, cross, radius2")]
+    pub neighborhood: Neighborhood,
+
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=9), default_value_t = 1, help = "Let a single cell hold up to N mines")]
+    pub max_mines: u8,
+
+    #[arg(short, long, value_name = "FILE", help = "Play a hand written board instead of a random one")]
+    pub board: Option<String>,
+
+    #[arg(short, long, value_name = "NAME", value_parser = preset_name, help = "Use a preset board: beginner, intermediate, expert")]
+    pub preset: Option<&'static Preset>,
+
+    #[arg(long, conflicts_with_all = ["width", "height", "difficulty", "board", "preset", "challenge", "wrap", "hex", "neighborhood", "max_mines"], help = "Play today's daily challenge, the same board for everyone")]
+    pub daily: bool,
+
+    #[arg(long, value_name = "CODE", value_parser = challenge, conflicts_with_all = ["width", "height", "difficulty", "board", "preset", "wrap", "hex", "neighborhood", "max_mines"], help = "Play the exact board from someone else's shared result")]
+    pub challenge: Option<Challenge>,
+
+    #[arg(long, help = "Print a shareable result with a board thumbnail after the game")]
+    pub share: bool,
+
+    #[arg(long, value_name = "FILE", help = "Write that shareable result to FILE instead")]
+    pub share_file: Option<String>,
+
+    #[arg(long, help = "Draw the shared thumbnail with plain characters instead of emoji")]
+    pub ascii: bool,
+
+    #[arg(short, long, help = "Draw cells without borders so bigger boards fit")]
+    pub compact: bool,
+
+    #[arg(short = 't', long, help = "Hide the game clock")]
+    pub hide_timer: bool,
+
+    #[arg(short, long, value_name = "ITEMS", value_parser = StatusBar::parse, default_value = status::DEFAULT_ITEMS, help = "What the status bar shows, any of mines, cells, 3bv, cps, seed, preset or none")]
+    pub status: StatusBar,
+
+    #[arg(long, help = "Join the board edges so every cell has 8 neighbors")]
+    pub wrap: bool,
+
+    #[arg(long, conflicts_with = "neighborhood", help = "Play on a hexagonal grid where each cell has 6 neighbors")]
+    pub hex: bool,
+
+    #[arg(long, conflicts_with_all = ["daily", "max_mines"], help = "Two players take turns on one board and score points for cells and found mines")]
+    pub versus: bool,
+
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["daily", "versus", "host", "join", "watch"], help = "Survive N - 1 mines, each one blown up costs a life and the last one ends the game")]
+    pub lives: Option<u32>,
+
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["daily", "board", "versus"], help = "Host a race on PORT, everyone plays the same board and sees the others' progress")]
+    pub host: Option<u16>,
+
+    #[arg(long, requires = "host", help = "Make the hosted game co-op, everyone plays one shared board and one mine ends it for all")]
+    pub coop: bool,
+
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["host", "daily", "board", "versus", "challenge", "preset", "width", "height", "difficulty", "neighborhood", "max_mines", "wrap", "hex"], help = "Join the race or co-op game hosted at ADDR, e.g. 192.168.1.20:7878")]
+    pub join: Option<String>,
+
+    #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7870", help = "Let other terminals on this machine watch the game live [default port: 7870]")]
+    pub publish: Option<u16>,
+
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = "127.0.0.1:7870", conflicts_with_all = ["width", "height", "difficulty", "board", "preset", "daily", "challenge", "versus", "host", "join", "publish", "share", "share_file", "wrap", "hex", "max_mines"], help = "Watch a game published with --publish [default: 127.0.0.1:7870]")]
+    pub watch: Option<String>,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+// the automatic help flag is switched off for every command since -h is taken, so each one adds --help back
+#[derive(Args)]
+pub struct HelpArgs {
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    #[arg(value_name = "FILE", help = "The .rawvf replay to watch")]
+    pub file: String,
+
+    #[arg(short, long, help = "Draw cells without borders so bigger boards fit")]
+    pub compact: bool,
+
+    #[arg(short = 't', long, help = "Hide the game clock")]
+    pub hide_timer: bool,
+
+    #[arg(short, long, value_name = "ITEMS", value_parser = StatusBar::parse, default_value = status::DEFAULT_ITEMS, help = "What the status bar shows, any of mines, cells, 3bv, cps, seed, preset or none")]
+    pub status: StatusBar,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct SolveArgs {
+    #[arg(value_name = "FILE", help = "A board file, see --board")]
+    pub file: String,
+
+    #[arg(short, long, value_name = "RULE", value_parser = neighborhood, default_value = "moore", help = "Which cells a number counts on square grids")]
+    pub neighborhood: Neighborhood,
+
+    #[arg(long, help = "Read the board as joined at the edges")]
+    pub wrap: bool,
+
+    #[arg(long, conflicts_with = "neighborhood", help = "Read the board as a hexagonal grid")]
+    pub hex: bool,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    #[arg(long, value_name = "PORT", default_value_t = 2323, help = "Port to accept telnet connections on")]
+    pub port: u16,
+
+    #[arg(short, long, value_name = "NAME", value_parser = preset_name, default_value = "beginner", help = "The board every connection plays: beginner, intermediate, expert")]
+    pub preset: &'static Preset,
+
+    #[arg(short, long, help = "Start every connection with borderless cells")]
+    pub compact: bool,
+
+    #[arg(short, long, value_name = "ITEMS", value_parser = StatusBar::parse, default_value = status::DEFAULT_ITEMS, help = "What the status bar shows, any of mines, cells, 3bv, cps, seed, preset or none")]
+    pub status: StatusBar,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct TutorialArgs {
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=tutorial::LESSONS.len() as i64), default_value_t = 1, help = "Start at lesson N, later lessons build on the earlier ones")]
+    pub lesson: u8,
+
+    #[arg(short, long, help = "Draw cells without borders so the boards fit smaller terminals")]
+    pub compact: bool,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct DrillArgs {
+    #[arg(short, long, value_name = "LEVEL", value_parser = grade, default_value = "medium", help = "How much of the board the proven move takes reading: easy, medium, hard")]
+    pub difficulty: Grade,
+
+    #[arg(short, long, help = "Draw cells without borders so the boards fit smaller terminals")]
+    pub compact: bool,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+#[derive(Args)]
+pub struct EndlessArgs {
+    #[arg(short, long, value_name = "LEVEL", value_parser = difficulty, default_value = "medium", help = "Share of cells that are mines: easy, medium, hard")]
+    pub difficulty: Difficulty,
+
+    #[arg(short, long, value_name = "RULE", value_parser = neighborhood, default_value = "moore", help = "Which cells a number counts: moore, knight, cross, radius2")]
+    pub neighborhood: Neighborhood,
+
+    #[arg(long, value_name = "N", help = "Grow the same world again, the seed is shown while playing")]
+    pub seed: Option<u64>,
+
+    #[arg(short, long, help = "Draw cells without borders so more of the world fits")]
+    pub compact: bool,
+
+    #[arg(long, action = ArgAction::Help, help = "Print help")]
+    pub help: Option<bool>,
+}
+
+fn board_side(value: &str) -> Result<usize, String> {
+    let side: usize = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+    if !(2..=MAX_BOARD_SIDE).contains(&side) { return Err(format!("must be between 2 and {}", MAX_BOARD_SIDE)); }
+    Ok(side)
+}
+
+fn difficulty(value: &str) -> Result<Difficulty, String> {
+    match value.to_lowercase().as_str() {
+        "easy" | "e" => Ok(Difficulty::EASY),
+        "medium" | "m" => Ok(Difficulty::MEDIUM),
+        "hard" | "h" => Ok(Difficulty::HARD),
+        _ => Err("expected easy, medium or hard".to_string()),
+    }
+}
+
+fn grade(value: &str) -> Result<Grade, String> {
+    Grade::parse(value).ok_or_else(|| "expected easy, medium or hard".to_string())
+}
+
+fn neighborhood(value: &str) -> Result<Neighborhood, String> {
+    Neighborhood::parse(value).ok_or_else(|| "expected moore, knight, cross or radius2".to_string())
+}
+
+fn preset_name(value: &str) -> Result<&'static Preset, String> {
+    let names: Vec<&str> = preset::PRESETS.iter().map(|preset| preset.name).collect();
+    preset::find(value).ok_or_else(|| format!("expected one of {}", names.join(", ")))
+}
+
+fn challenge(value: &str) -> Result<Challenge, String> {
+    let challenge = Challenge::decode(value).map_err(|err| err.to_string())?;
+    if challenge.width > MAX_BOARD_SIDE || challenge.height > MAX_BOARD_SIDE { return Err("the board is too big".to_string()); }
+    Ok(challenge)
+}
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+// where the game reads the time from, swapped out for replays so the clock follows the recording
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant { Instant::now() }
+}
+
+// the game timer. it is handed the time on every call instead of reading a clock itself, runs from start
+// to stop and leaves out every pause in between
+#[derive(Default)]
+pub struct Stopwatch {
+    started_at: Option<Instant>,
+    stopped_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    paused_for: Duration, // every finished pause
+}
+
+impl Stopwatch {
+    pub fn start(&mut self, now: Instant) {
+        if self.started_at.is_none() { self.started_at = Some(now); }
+    }
+
+    pub fn stop(&mut self, now: Instant) {
+        if self.started_at.is_none() || self.stopped_at.is_some() { return; }
+        self.resume(now);
+        self.stopped_at = Some(now);
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.stopped_at.is_none() && self.paused_at.is_none() { self.paused_at = Some(now); }
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        let Some(paused_at) = self.paused_at.take() else { return };
+        if let Some(started_at) = self.started_at { // a pause from before the start never counted anyway
+            self.paused_for += now.saturating_duration_since(paused_at.max(started_at));
+        }
+    }
+
+    pub fn is_paused(&self) -> bool { self.paused_at.is_some() }
+
+    // saturating all the way through, so a clock that jumps backwards can never make it underflow
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        let Some(started_at) = self.started_at else { return Duration::ZERO };
+        let end = self.stopped_at.or(self.paused_at).unwrap_or(now).max(started_at);
+        end.saturating_duration_since(started_at).saturating_sub(self.paused_for)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(origin: Instant, seconds: u64) -> Instant { origin + Duration::from_secs(seconds) }
+
+    #[test]
+    fn runs_from_start_to_stop() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        assert_eq!(stopwatch.elapsed(at(origin, 5)), Duration::ZERO);
+
+        stopwatch.start(at(origin, 1));
+        stopwatch.start(at(origin, 2)); // only the first start counts
+        assert_eq!(stopwatch.elapsed(at(origin, 4)), Duration::from_secs(3));
+
+        stopwatch.stop(at(origin, 6));
+        assert_eq!(stopwatch.elapsed(at(origin, 60)), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn pauses_are_left_out() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        stopwatch.start(origin);
+        stopwatch.pause(at(origin, 2));
+        assert!(stopwatch.is_paused());
+        assert_eq!(stopwatch.elapsed(at(origin, 10)), Duration::from_secs(2));
+
+        stopwatch.resume(at(origin, 10));
+        assert!(!stopwatch.is_paused());
+        assert_eq!(stopwatch.elapsed(at(origin, 11)), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn stopping_while_paused_keeps_the_pause_out() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        stopwatch.start(origin);
+        stopwatch.pause(at(origin, 2));
+        stopwatch.stop(at(origin, 7));
+        assert_eq!(stopwatch.elapsed(at(origin, 20)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn a_pause_before_the_start_is_ignored() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        stopwatch.pause(origin);
+        stopwatch.resume(at(origin, 5));
+        stopwatch.start(at(origin, 6));
+        assert_eq!(stopwatch.elapsed(at(origin, 8)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn elapsed_never_goes_backwards() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        let mut last = Duration::ZERO;
+        for second in 0..40 {
+            let now = at(origin, second);
+            match second {
+                3 => stopwatch.start(now),
+                8 | 20 => stopwatch.pause(now),
+                12 | 25 => stopwatch.resume(now),
+                30 => stopwatch.stop(now),
+                _ => {}
+            }
+            let elapsed = stopwatch.elapsed(now);
+            assert!(elapsed >= last, "went from {:?} back to {:?} at {}s", last, elapsed, second);
+            last = elapsed;
+        }
+        assert_eq!(last, Duration::from_secs(18));
+    }
+
+    #[test]
+    fn a_clock_that_jumps_back_does_not_underflow() {
+        let origin = Instant::now();
+        let mut stopwatch = Stopwatch::default();
+        stopwatch.start(at(origin, 10));
+        stopwatch.pause(at(origin, 12));
+        stopwatch.resume(at(origin, 11)); // resumed before it was paused
+        assert_eq!(stopwatch.elapsed(origin), Duration::ZERO);
+        assert_eq!(stopwatch.elapsed(at(origin, 13)), Duration::from_secs(3));
+    }
+}
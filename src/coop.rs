@@ -0,0 +1,150 @@
+use std::net::TcpListener;
+
+use tui::style::Color;
+
+use crate::game::{Action, Game, Team};
+use crate::net::{Link, Peer, Session};
+
+// everyone works on one board. the host's game is the one that counts: joiners only ask for a move and every
+// player, the host included, plays the moves in the order the host took them in, so two players going for the
+// same cell end up with the same board everywhere and one mine ends the game for the whole team:
+//   ACT <reveal|flag> <x> <y>             joiner to host
+//   DO <player> <reveal|flag> <x> <y>     host to everyone, every move so far is replayed to a new joiner
+//   CURSOR <player> <x> <y>               anyone, relayed by the host
+//   LEFT <player>                         host, when a joiner hangs up
+pub struct Coop {
+    link: Link,
+    log: Vec<String>, // every move the host took, in order
+    moves: Vec<usize>, // how many moves each player made, indexed by player
+    left: Vec<usize>,
+    sent_cursor: Option<(usize, usize)>,
+}
+
+impl Coop {
+    pub fn host(listener: TcpListener, code: String) -> Self {
+        Coop { link: Link::host(listener, code, "coop"), log: Vec::new(), moves: vec![0], left: Vec::new(), sent_cursor: None }
+    }
+
+    pub fn joined(host: Peer, player: usize) -> Self {
+        Coop { link: Link::joined(host, player), log: Vec::new(), moves: vec![0; player + 1], left: Vec::new(), sent_cursor: None }
+    }
+
+    fn welcome(&mut self, game: &Game, peer: Peer) {
+        let mut catch_up: String = self.log.iter().map(|line| format!("{}\n", line)).collect(); // the board so far, move by move
+        if let Some(team) = &game.team {
+            for (player, cursor) in team.cursors.iter().enumerate() {
+                if let Some((x, y)) = cursor { catch_up.push_str(&format!("CURSOR {} {} {}\n", player, x, y)); }
+            }
+        }
+        for player in &self.left { catch_up.push_str(&format!("LEFT {}\n", player)); }
+
+        self.count_player(peer.player);
+        self.link.add(peer, &catch_up);
+    }
+
+    // the host takes a move, from anyone, and has everyone play it
+    fn take(&mut self, game: &mut Game, player: usize, action: Action, x: usize, y: usize) {
+        if !game.apply_at(x, y, action) { return; }
+
+        let line = format!("DO {} {} {} {}", player, action_name(action), x, y);
+        self.link.broadcast(&line, None);
+        self.log.push(line);
+        self.count_move(player);
+    }
+
+    fn handle(&mut self, game: &mut Game, from: usize, line: &str) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            ["ACT", action, x, y] if self.link.is_host() => {
+                let (Some(action), Ok(x), Ok(y)) = (parse_action(action), x.parse(), y.parse()) else { return };
+                self.take(game, from, action, x, y);
+            }
+            ["DO", player, action, x, y] if !self.link.is_host() => {
+                let (Ok(player), Some(action), Ok(x), Ok(y)) = (player.parse(), parse_action(action), x.parse(), y.parse()) else { return };
+                game.apply_at(x, y, action);
+                self.count_move(player);
+            }
+            ["CURSOR", player, x, y] => {
+                let (Ok(player), Ok(x), Ok(y)) = (player.parse::<usize>(), x.parse(), y.parse()) else { return };
+                if player == self.link.player || (self.link.is_host() && player != from) { return; } // no one speaks for someone else
+                if let Some(team) = &mut game.team { team.set_cursor(player, Some((x, y))); }
+                self.count_player(player);
+                self.link.relay(line, from);
+            }
+            ["LEFT", player] if !self.link.is_host() => {
+                let Ok(player) = player.parse() else { return };
+                self.mark_left(game, player);
+            }
+            _ => {}
+        }
+    }
+
+    fn mark_left(&mut self, game: &mut Game, player: usize) {
+        if let Some(team) = &mut game.team { team.set_cursor(player, None); }
+        self.count_player(player);
+        if !self.left.contains(&player) { self.left.push(player); }
+    }
+
+    fn count_player(&mut self, player: usize) {
+        if self.moves.len() <= player { self.moves.resize(player + 1, 0); }
+    }
+
+    fn count_move(&mut self, player: usize) {
+        self.count_player(player);
+        self.moves[player] += 1;
+    }
+}
+
+impl Session for Coop {
+    fn poll(&mut self, game: &mut Game) {
+        for peer in self.link.accept() { self.welcome(game, peer); }
+
+        let (received, left) = self.link.receive();
+        for (from, line) in received { self.handle(game, from, &line); }
+        for player in left { self.mark_left(game, player); }
+
+        let cursor = game.cursor();
+        if self.sent_cursor != Some(cursor) {
+            self.link.broadcast(&format!("CURSOR {} {} {}", self.link.player, cursor.0, cursor.1), None);
+            self.sent_cursor = Some(cursor);
+        }
+    }
+
+    // nothing happens until the host has put the move in order, even for the host's own moves
+    fn act(&mut self, game: &mut Game, action: Action) -> bool {
+        let (x, y) = game.cursor();
+        if self.link.is_host() { self.take(game, self.link.player, action, x, y); }
+        else { self.link.broadcast(&format!("ACT {} {} {}", action_name(action), x, y), None); }
+        true
+    }
+
+    // everyone on the team in their cursor color
+    fn panel(&self) -> Vec<(String, Color)> {
+        let mut lines = vec![(format!("Co-op, you are P{}", self.link.player + 1), Team::color(self.link.player))];
+        for (player, moves) in self.moves.iter().enumerate() {
+            let name = format!("Player {}", player + 1);
+            let line =
+                if self.left.contains(&player) { format!("{:<10}left", name) }
+                else { format!("{:<10}{} moves", name, moves) };
+            lines.push((line, Team::color(player)));
+        }
+        if self.moves.len() == 1 { lines.push(("Waiting for players".to_string(), Color::White)); }
+        if self.link.host_left { lines.push(("The host has left".to_string(), Color::White)); }
+        lines
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Reveal => "reveal",
+        Action::Flag => "flag",
+    }
+}
+
+fn parse_action(name: &str) -> Option<Action> {
+    match name {
+        "reveal" => Some(Action::Reveal),
+        "flag" => Some(Action::Flag),
+        _ => None,
+    }
+}
@@ -0,0 +1,78 @@
+use std::{any::Any, fs, io, panic, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableFocusChange, DisableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
+};
+
+use crate::game::{Game, MoveKind};
+use crate::layout;
+use crate::stats;
+
+// puts the terminal back the way the shell left it, safe to call more than once
+pub fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, Show)
+}
+
+// held for as long as the game owns the terminal, dropping it restores the terminal on every way out,
+// including an early return through '?'
+pub struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+// the guard only drops once unwinding reaches it, after the panic message has already been printed to the
+// alternate screen and lost, so the hook restores the terminal first and then lets the usual message through
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() { message.to_string() }
+    else if let Some(message) = payload.downcast_ref::<String>() { message.clone() }
+    else { "unknown panic".to_string() }
+}
+
+// everything needed to play the crashed game back: the seed, a challenge code for the same board, every move and
+// the mine layout, written next to the stats or in the current directory when there is no home directory
+pub fn write_report(game: &Game, challenge: Option<String>, message: &str) -> io::Result<PathBuf> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let board = game.board();
+
+    let mut text = format!("Termsweeper {} crash report\n", env!("CARGO_PKG_VERSION"));
+    text.push_str(&format!("Timestamp: {}\n", stamp));
+    text.push_str(&format!("Panic: {}\n", message));
+    text.push_str(&format!("Board: {}x{} {}, {} mine(s) per cell\n", board.width(), board.height(), board.topology().name(), board.max_mines()));
+    text.push_str(&format!("Seed: {:x}\n", game.seed));
+    if let Some((x, y)) = game.start { text.push_str(&format!("Start: {} {}\n", x, y)); }
+    if let Some(code) = challenge { text.push_str(&format!("Challenge: {}\n", code)); }
+
+    text.push_str("Moves:\n");
+    for step in &game.moves {
+        let kind = match step.kind {
+            MoveKind::Reveal => "reveal",
+            MoveKind::Chord => "chord",
+            MoveKind::Flag => "flag",
+        };
+        text.push_str(&format!("{:.3} {} {} {}\n", step.time.as_secs_f64(), kind, step.x, step.y));
+    }
+
+    text.push_str("Layout:\n");
+    text.push_str(&layout::export(board, true));
+
+    let dir = stats::data_dir().map(|dir| dir.join("crashes")).unwrap_or_default();
+    if !dir.as_os_str().is_empty() { fs::create_dir_all(&dir)?; }
+    let path = dir.join(format!("crash-{}.txt", stamp));
+    fs::write(&path, text)?;
+    Ok(path)
+}
@@ -0,0 +1,186 @@
+use std::{cell::Cell, rc::Rc};
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use tui::style::Color;
+
+use crate::board::Board;
+use crate::game::{Action, CellState, Difficulty, Game, GameState};
+use crate::layout::{self, Layout};
+use crate::net::Session;
+use crate::solver::{self, Deduction};
+use crate::topology::{self, Neighborhood};
+
+const WIDTH: usize = 9;
+const HEIGHT: usize = 9;
+const ATTEMPTS: usize = 300; // boards tried for the wanted grade before settling for any puzzle
+
+// partly solved boards where the numbers prove exactly one move. a board is played forward from a safe start
+// by its proven moves, one at a time, until it reaches a point where only one is left. how hard the puzzle is
+// depends on how much of the board has to be read together to find it:
+//   easy    one number on its own
+//   medium  two numbers together, like a 1-2-1
+//   hard    more than two
+#[derive(Clone, Copy, PartialEq)]
+pub enum Grade {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Grade {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "easy" | "e" => Some(Grade::Easy),
+            "medium" | "m" => Some(Grade::Medium),
+            "hard" | "h" => Some(Grade::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Grade::Easy => "easy",
+            Grade::Medium => "medium",
+            Grade::Hard => "hard",
+        }
+    }
+}
+
+pub struct Puzzle {
+    pub game: Game,
+    pub answer: Deduction,
+}
+
+// None only if not one of the boards tried came to a single proven move, of any grade
+pub fn generate(grade: Grade) -> Option<Puzzle> {
+    let mut fallback = None;
+    for _ in 0..ATTEMPTS {
+        match attempt(grade, thread_rng().gen()) {
+            (Some(puzzle), _) => { return Some(puzzle); }
+            (None, Some(other)) => { fallback = Some(other); }
+            (None, None) => {}
+        }
+    }
+    fallback
+}
+
+// the puzzle of the wanted grade if the board gets to one, else the last puzzle of another grade it passed
+fn attempt(grade: Grade, seed: u64) -> (Option<Puzzle>, Option<Puzzle>) {
+    let topology = topology::build(WIDTH, HEIGHT, false, false, Neighborhood::Moore);
+    let mut game = Game::new(topology, Difficulty::HARD).with_seed(seed).start_at_center();
+    let (x, y) = game.cursor();
+    game.apply_at(x, y, Action::Reveal);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut other = None;
+    while game.game_state == GameState::ACTIVE {
+        let board = game.board();
+        let known = solver::prove(board);
+        let open: Vec<Deduction> = (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+            .filter(|&(x, y)| board.state(x, y) == CellState::HIDDEN)
+            .filter_map(|(x, y)| known[board.index(x, y)].map(|mines| Deduction { x, y, is_mine: mines > 0 }))
+            .collect();
+
+        if open.is_empty() { break; } // only a guess goes on from here
+        if let [answer] = open[..] {
+            let puzzle = Puzzle { game: snapshot(board), answer };
+            if grade_of(board, answer) == grade { return (Some(puzzle), other); }
+            other = Some(puzzle);
+        }
+
+        let next = open[rng.gen_range(0..open.len())];
+        game.apply_at(next.x, next.y, if next.is_mine { Action::Flag } else { Action::Reveal });
+    }
+    (None, other)
+}
+
+fn grade_of(board: &Board, answer: Deduction) -> Grade {
+    let index = board.index(answer.x, answer.y);
+    if solver::deduce(board)[index].is_some() { Grade::Easy }
+    else if solver::prove_in_pairs(board)[index].is_some() { Grade::Medium }
+    else { Grade::Hard }
+}
+
+// a fresh game of the board as it stands, so the clock and the move list start over
+fn snapshot(board: &Board) -> Game {
+    let layout = Layout::parse(&layout::export(board, true)).expect("an exported board parses");
+    let topology = topology::build(WIDTH, HEIGHT, false, false, Neighborhood::Moore);
+    let mut game = Game::from_layout(topology, &layout).expect("an exported board fits its size");
+    game.message = Some("Arrows move, f flags, Enter reveals, 'q' leaves".to_string());
+    game
+}
+
+// one puzzle after another, every answer counted. the score is shared so it can be printed once the run is over
+pub struct Drill {
+    grade: Grade,
+    answer: Deduction,
+    puzzle: usize,
+    answered: Option<bool>, // whether the answer was right, once it is given
+    score: Rc<Cell<(usize, usize)>>, // right answers and puzzles answered
+}
+
+impl Drill {
+    pub fn start(grade: Grade, score: Rc<Cell<(usize, usize)>>) -> Option<(Self, Game)> {
+        let puzzle = generate(grade)?;
+        Some((Drill { grade, answer: puzzle.answer, puzzle: 1, answered: None, score }, puzzle.game))
+    }
+
+    fn answer(&mut self, game: &mut Game, right: bool) {
+        let (correct, answered) = self.score.get();
+        self.score.set((correct + right as usize, answered + 1));
+        self.answered = Some(right);
+        game.hint = Some(self.answer); // marks the move that was proven, right or wrong
+    }
+
+    fn next_puzzle(&mut self, game: &mut Game) {
+        let Some(puzzle) = generate(self.grade) else {
+            game.message = Some("No puzzle turned up this time, press Enter to look again".to_string());
+            return;
+        };
+        let compact = game.compact;
+        *game = puzzle.game;
+        game.compact = compact;
+        self.answer = puzzle.answer;
+        self.puzzle += 1;
+        self.answered = None;
+    }
+}
+
+impl Session for Drill {
+    // asking for a hint gives the answer away, so it counts as a miss
+    fn poll(&mut self, game: &mut Game) {
+        if self.answered.is_none() && game.hint.is_some() { self.answer(game, false); }
+    }
+
+    // the move is only ever judged, never played, so a wrong reveal cannot end the run
+    fn act(&mut self, game: &mut Game, action: Action) -> bool {
+        if self.answered.is_some() {
+            self.next_puzzle(game);
+            return true;
+        }
+
+        let (x, y) = game.cursor();
+        if game.board().state(x, y) != CellState::HIDDEN { return true; } // numbers and the proven flags are not an answer
+        let right = (x, y) == (self.answer.x, self.answer.y) && (action == Action::Flag) == self.answer.is_mine;
+        self.answer(game, right);
+        true
+    }
+
+    fn panel(&self) -> Vec<(String, Color)> {
+        let (correct, answered) = self.score.get();
+        let accuracy = (correct * 100).checked_div(answered).map(|percent| format!(" ({}%)", percent)).unwrap_or_default();
+        let mut lines = vec![
+            (format!("Drill, {}", self.grade.name()), Color::Yellow),
+            (format!("Puzzle {}", self.puzzle), Color::White),
+            (format!("{} of {} right{}", correct, answered, accuracy), Color::White),
+            (String::new(), Color::White),
+        ];
+        match self.answered {
+            None => lines.push(("Exactly one move is proven here. Reveal the safe cell or flag the mine.".to_string(), Color::White)),
+            Some(true) => lines.push(("Right!".to_string(), Color::Green)),
+            Some(false) => lines.push(("Not that one, the proven move is marked".to_string(), Color::LightRed)),
+        }
+        if self.answered.is_some() { lines.push(("Press Enter for the next puzzle".to_string(), Color::White)); }
+        lines
+    }
+}
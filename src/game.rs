@@ -1,7 +1,12 @@
-use std::time::{Duration, Instant};
+use std::{io, iter, time::{Duration, Instant}};
 
-use crate::helpers::{incriment_neighbors, reveal_safe_neighbors};
-use crossterm::event::KeyCode;
+use crate::analysis::Analysis;
+use crate::board::Board;
+use crate::clock::{Clock, Stopwatch, SystemClock};
+use crate::helpers::{self, incriment_neighbors, reveal_safe_neighbors};
+use crate::layout::Layout;
+use crate::solver::{self, Deduction};
+use crate::topology::{self, Direction, Neighborhood, Topology};
 use rand::{thread_rng, Rng};
 use tui::{ 
     backend::Backend, 
@@ -13,17 +18,29 @@ use tui::{
 
 pub(crate) const CELL_WIDTH: u16 = 5;
 pub(crate) const CELL_HEIGHT: u16 = 3;
+pub(crate) const COMPACT_CELL_WIDTH: u16 = 3; // compact cells drop their borders
+pub(crate) const COMPACT_CELL_HEIGHT: u16 = 1;
 pub(crate) const END_ANIMATION_DELAY: Duration = Duration::from_millis(125);
+pub(crate) const VERSUS_MINE_POINTS: i32 = 5;
+const ENDLESS_MINE_POINTS: i64 = 25;
+const ENDLESS_SIDE: usize = 1 << 24; // millions of cells from the middle to any edge, nobody walks that far
+const SCROLL_MARGIN: usize = 2; // cells kept between the cursor and the edge of the screen on an endless board
+pub(crate) const PLAYER_COLORS: [Color; 2] = [Color::Blue, Color::Magenta];
+pub(crate) const TEAM_COLORS: [Color; 6] = [Color::Blue, Color::Magenta, Color::Green, Color::Yellow, Color::Cyan, Color::LightRed]; // reused past six players
 
 #[derive(PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum GameState {
     ACTIVE,
     SUCSESS,
     FAILED,
+    WINNER(usize), // a versus game that ended with one player ahead
+    DRAW,
 }
 
 #[derive(Clone)]
 #[repr(usize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Difficulty { // dictates the percentage of cells that should be mines
     EASY = 12,
     MEDIUM = 16,
@@ -31,7 +48,7 @@ pub enum Difficulty { // dictates the percentage of cells that should be mines
 }
 
 pub struct Game {
-    grid: Vec<Vec<Cell>>,
+    board: Board,
     cursor_x: usize,
     cursor_y: usize,
     width: usize,
@@ -40,20 +57,103 @@ pub struct Game {
     difficulty_level: Difficulty,
     pub game_state: GameState,
     first_move_made: bool,
+    pub seed: u64, // together with the first click this decides where every mine goes
+    pub start: Option<(usize, usize)>, // the first click, once the mines have been laid out around it
+    layout: Option<Layout>, // the board file a loaded game started from, with the cells it already had open
     pub game_end_animation_level: usize,
-    pub game_time: Instant,
-    pub game_start_time: Instant,
-    pub game_end_time: Instant,
+    end_animation_time: Instant, // when the end animation last grew, wall time since it is only for show
+    clock: Box<dyn Clock>,
+    timer: Stopwatch, // runs from the first reveal until the game is won or lost
     pub flags_available: i32,
     pub hidden_cells_remaining: usize,
+    pub hint: Option<Deduction>,
+    pub message: Option<String>,
+    pub compact: bool,
+    pub versus: Option<Versus>,
+    pub team: Option<Team>,
+    pub lives: Option<Lives>,
+    pub endless: Option<Endless>,
+    pub analysis: Option<Analysis>, // shown over the finished board
+    three_bv_cache: Option<(usize, (usize, usize))>, // 3BV progress and the move count it was worked out at
+    pub moves: Vec<Move>,
 }
 
-pub struct Cell {
-    pub mines_seen: i8,
-    pub cell_state: CellState,
+// hot seat play, players take turns on one board and race for points instead of the clock. finding a mine
+// with a flag scores and keeps the turn, a reveal scores a point per cell it uncovers and passes the turn on,
+// a wrong flag gives the cell away for nothing and blowing up a mine costs points
+pub struct Versus {
+    pub scores: Vec<i32>,
+    pub turn: usize,
+    owners: Vec<Option<usize>>, // who claimed each flagged mine, indexed like the board
 }
 
-#[derive(PartialEq)]
+impl Versus {
+    pub fn owner(&self, index: usize) -> Option<usize> { self.owners[index] }
+
+    fn pass_turn(&mut self) { self.turn = (self.turn + 1) % self.scores.len(); }
+}
+
+// a mine costs a life instead of the game, it stays on the board blown up and counts like a flag
+#[derive(Clone, Copy)]
+pub struct Lives {
+    pub left: u32,
+    pub total: u32,
+}
+
+// a board that goes on and on, every revealed cell scores a point and a mine costs points instead of the game.
+// only the part around the cursor fits on screen, so the window onto the board scrolls with it
+pub struct Endless {
+    pub revealed: usize,
+    pub mines_hit: usize,
+    window: Option<(usize, usize)>, // the top left cell on screen, None until the screen size is known
+    columns: usize,
+    rows: usize,
+}
+
+impl Endless {
+    pub fn score(&self) -> i64 { self.revealed as i64 - self.mines_hit as i64 * ENDLESS_MINE_POINTS }
+}
+
+// co-op play, everyone works on one board and sees where the others are
+pub struct Team {
+    pub player: usize, // whose screen this is
+    pub cursors: Vec<Option<(usize, usize)>>, // indexed by player, None until they move or once they left
+}
+
+impl Team {
+    pub fn color(player: usize) -> Color { TEAM_COLORS[player % TEAM_COLORS.len()] }
+
+    pub fn set_cursor(&mut self, player: usize, cursor: Option<(usize, usize)>) {
+        if self.cursors.len() <= player { self.cursors.resize(player + 1, None); }
+        self.cursors[player] = cursor;
+    }
+}
+
+// what a player does to a cell, how networked games pass moves around
+#[derive(PartialEq, Clone, Copy)]
+pub enum Action {
+    Reveal, // a chord on revealed numbers
+    Flag,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum MoveKind {
+    Reveal,
+    Chord,
+    Flag,
+}
+
+// one player action, timed from the start of the game
+#[derive(Clone, Copy)]
+pub struct Move {
+    pub time: Duration,
+    pub kind: MoveKind,
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
 pub enum CellState {
     HIDDEN,
     REVEALED,
@@ -62,24 +162,27 @@ pub enum CellState {
 }
 
 impl Game {
-    pub fn new(width: usize, height: usize, difficulty: Difficulty) -> Self {
-        let mut grid= Vec::with_capacity(height);
-        
-        for _ in 0..height {
-            let mut row: Vec<Cell> = Vec::with_capacity(width);
-            for _ in 0..width {
-                row.push(Cell {
-                    mines_seen: 0,
-                    cell_state: CellState::HIDDEN,
-                });
-            }
-            grid.push(row);
-        }
+    pub fn new(topology: Box<dyn Topology>, difficulty: Difficulty) -> Self {
+        Game::on(Board::new(topology, 1), difficulty)
+    }
+
+    // starts in the middle of a square board too big to ever finish, see Endless
+    pub fn endless(neighborhood: Neighborhood, difficulty: Difficulty) -> Self {
+        let topology = topology::build(ENDLESS_SIDE, ENDLESS_SIDE, false, false, neighborhood);
+        let mut game = Game::on(Board::endless(topology, difficulty.to_owned() as usize), difficulty);
+        game.cursor_x = ENDLESS_SIDE / 2;
+        game.cursor_y = ENDLESS_SIDE / 2;
+        game.flags_available = 0; // there is no telling how many mines there are
+        game.endless = Some(Endless { revealed: 0, mines_hit: 0, window: None, columns: 0, rows: 0 });
+        game
+    }
 
+    fn on(board: Board, difficulty: Difficulty) -> Self {
+        let (width, height) = (board.width(), board.height());
         let mines= width * height * difficulty.to_owned() as usize / 100; // truncate non integer mine count
 
         Game {
-            grid,
+            board,
             cursor_x: 0,
             cursor_y: 0,
             width,
@@ -88,60 +191,255 @@ impl Game {
             difficulty_level: difficulty,
             game_state: GameState::ACTIVE,
             first_move_made: false,
+            seed: thread_rng().gen(),
+            start: None,
+            layout: None,
             game_end_animation_level: 0,
-            game_time: Instant::now(),
-            game_start_time: Instant::now(),
-            game_end_time: Instant::now(),
+            end_animation_time: Instant::now(),
+            clock: Box::new(SystemClock),
+            timer: Stopwatch::default(),
             flags_available: mines as i32,
             hidden_cells_remaining: width * height - mines,
+            hint: None,
+            message: None,
+            compact: false,
+            versus: None,
+            team: None,
+            lives: None,
+            endless: None,
+            analysis: None,
+            three_bv_cache: None,
+            moves: Vec::new(),
         }
     }
-    
-    pub fn move_cursor(&mut self, direction: KeyCode) {
-        match direction {
-            KeyCode::Up => {
-                if self.cursor_y > 0 { 
-                    self.cursor_y -= 1;
-                }
-            },
-            KeyCode::Down => {
-                if self.cursor_y < self.height - 1 {
-                    self.cursor_y += 1;
-                }
-            },
-            KeyCode::Left => {
-                if self.cursor_x > 0 {
-                    self.cursor_x -= 1;
+
+    // a hand written board, its mines are fixed so place_mines never runs
+    pub fn from_layout(topology: Box<dyn Topology>, layout: &Layout) -> io::Result<Self> {
+        if topology.width() != layout.width || topology.height() != layout.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the board topology does not match the layout size"));
+        }
+        if topology.is_hex() && topology.wraps() && layout.height % 2 == 1 { // offset rows only line up across the seam with an even row count
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "a wrapped hex board needs an even number of rows"));
+        }
+
+        let mut game = Game::new(topology, Difficulty::MEDIUM).with_max_mines(layout.max_mines());
+        let mut mines = 0;
+        let mut mine_cells = 0;
+
+        for y in 0..layout.height {
+            for x in 0..layout.width {
+                let cell_mines = layout.mines[y * layout.width + x];
+                for _ in 0..cell_mines {
+                    game.board.add_mine(x, y);
+                    incriment_neighbors(x, y, &mut game.board);
                 }
-            },
-            KeyCode::Right => {
-                if self.cursor_x < self.width - 1 {
-                    self.cursor_x += 1;
+                mines += cell_mines as i32;
+                if cell_mines > 0 { mine_cells += 1; }
+            }
+        }
+
+        game.first_move_made = true;
+        game.layout = Some(layout.clone());
+        game.flags_available = mines;
+        game.hidden_cells_remaining = layout.width * layout.height - mine_cells;
+
+        if let Some(states) = &layout.states {
+            for y in 0..layout.height {
+                for x in 0..layout.width {
+                    let (state, flags) = states[y * layout.width + x];
+                    if state == CellState::REVEALED && game.board.is_mine(x, y) { // a board exported after a loss, show it as lost
+                        game.game_state = GameState::FAILED;
+                        game.cursor_x = x;
+                        game.cursor_y = y;
+                    } else if state == CellState::REVEALED {
+                        game.hidden_cells_remaining -= 1;
+                    }
+                    game.flags_available -= flags as i32;
+                    game.board.set_state(x, y, state);
+                    game.board.set_flags(x, y, flags);
                 }
-            },
-            _ => {}
+            }
+        }
+
+        if game.game_state == GameState::ACTIVE && game.hidden_cells_remaining == 0 { // nothing is left to reveal, so it was won already
+            let now = game.clock.now();
+            game.timer.start(now);
+            game.timer.stop(now);
+            game.game_state = GameState::SUCSESS;
+        }
+
+        Ok(game)
+    }
+
+    pub fn board(&self) -> &Board { &self.board }
+
+    pub fn layout(&self) -> Option<&Layout> { self.layout.as_ref() }
+    
+    // lets a single cell hold up to max_mines mines, flags then cycle through 1..=max_mines
+    pub fn with_max_mines(mut self, max_mines: u8) -> Self {
+        self.board.set_max_mines(max_mines);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // one player per color, taking turns from the first
+    pub fn with_versus(mut self) -> Self {
+        self.versus = Some(Versus { scores: vec![0; PLAYER_COLORS.len()], turn: 0, owners: vec![None; self.width * self.height] });
+        self
+    }
+
+    pub fn with_team(mut self, player: usize) -> Self {
+        self.team = Some(Team { player, cursors: Vec::new() });
+        self
+    }
+
+    pub fn with_lives(mut self, lives: u32) -> Self {
+        self.lives = Some(Lives { left: lives, total: lives });
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // lays the mines out straight away around a safe center start, so a seed alone decides the
+    // board no matter where the player clicks first
+    pub fn start_at_center(self) -> Self {
+        let (x, y) = (self.width / 2, self.height / 2);
+        self.start_at(x, y)
+    }
+
+    // same as start_at_center but from any cell, used to rebuild a shared game exactly
+    pub fn start_at(mut self, x: usize, y: usize) -> Self {
+        self.cursor_x = x.min(self.width - 1);
+        self.cursor_y = y.min(self.height - 1);
+        self.place_mines(self.cursor_x, self.cursor_y);
+        self.first_move_made = true;
+        self
+    }
+
+    pub fn cell_size(&self) -> (u16, u16) {
+        if self.compact { (COMPACT_CELL_WIDTH, COMPACT_CELL_HEIGHT) }
+        else { (CELL_WIDTH, CELL_HEIGHT) }
+    }
+
+    pub fn grid_size(&self) -> (u16, u16) {
+        let (cell_width, cell_height) = self.cell_size();
+        match &self.endless {
+            Some(endless) => (endless.columns as u16 * cell_width, endless.rows as u16 * cell_height),
+            None => self.board.topology().grid_size(cell_width, cell_height),
+        }
+    }
+
+    // the cells on screen as the top left one and how many columns and rows, the whole board unless it is endless
+    pub fn window(&self) -> (usize, usize, usize, usize) {
+        match &self.endless {
+            Some(Endless { window: Some((x, y)), columns, rows, .. }) => (*x, *y, *columns, *rows),
+            Some(_) => (0, 0, 0, 0),
+            None => (0, 0, self.width, self.height),
+        }
+    }
+
+    // gives an endless board as many columns and rows as the screen has room for, then scrolls just enough
+    // to keep the cursor on it
+    pub fn fit_window(&mut self, columns: usize, rows: usize) {
+        let (cursor_x, cursor_y) = (self.cursor_x, self.cursor_y);
+        let Some(endless) = &mut self.endless else { return };
+
+        let margin_x = SCROLL_MARGIN.min(columns.saturating_sub(1) / 2);
+        let margin_y = SCROLL_MARGIN.min(rows.saturating_sub(1) / 2);
+        let (x, y) = endless.window.unwrap_or((cursor_x.saturating_sub(columns / 2), cursor_y.saturating_sub(rows / 2)));
+        endless.window = Some((
+            x.clamp((cursor_x + margin_x + 1).saturating_sub(columns), cursor_x.saturating_sub(margin_x)),
+            y.clamp((cursor_y + margin_y + 1).saturating_sub(rows), cursor_y.saturating_sub(margin_y)),
+        ));
+        (endless.columns, endless.rows) = (columns, rows);
+    }
+
+    // worked out again only after a move, the status bar asks for it every frame
+    pub fn three_bv_progress(&mut self) -> (usize, usize) {
+        match self.three_bv_cache {
+            Some((moves, progress)) if moves == self.moves.len() => progress,
+            _ => {
+                let progress = self.board.three_bv_progress();
+                self.three_bv_cache = Some((self.moves.len(), progress));
+                progress
+            }
+        }
+    }
+
+    pub fn mines_placed(&self) -> bool { self.first_move_made }
+
+    pub fn started(&self) -> bool { !self.moves.is_empty() }
+
+    pub fn elapsed(&self) -> Duration { self.timer.elapsed(self.clock.now()) }
+
+    pub fn paused(&self) -> bool { self.timer.is_paused() }
+
+    // stops the clock and hides the board, only a game still in progress can be paused
+    pub fn pause(&mut self) {
+        if self.game_state == GameState::ACTIVE { self.timer.pause(self.clock.now()); }
+    }
+
+    pub fn resume(&mut self) { self.timer.resume(self.clock.now()); }
+
+    // a game that is only watched runs its clock from what the player's clock said last
+    pub fn show_elapsed(&mut self, elapsed: Duration, paused: bool) {
+        let now = self.clock.now();
+        self.timer = Stopwatch::default();
+        if elapsed.is_zero() && self.game_state == GameState::ACTIVE { return; } // still waiting for the first reveal
+
+        self.timer.start(now.checked_sub(elapsed).unwrap_or(now));
+        if self.game_state != GameState::ACTIVE { self.timer.stop(now); }
+        else if paused { self.timer.pause(now); }
+    }
+
+    pub fn move_cursor(&mut self, direction: Direction) {
+        if let Some((x, y)) = self.board.topology().step(self.cursor_x, self.cursor_y, direction) { // stops at the edge unless the board wraps
+            self.cursor_x = x;
+            self.cursor_y = y;
         }
     }
 
     pub fn place_mines(&mut self, centerx: usize, centery: usize){
-        let width = self.grid[0].len();
-        let height = self.grid.len();
-        let mines= self.grid[0].len() * self.grid.len() * self.difficulty_level.to_owned() as usize / 100; // truncate non integer mine count
-        let mut rng = thread_rng();
+        // the first click and every cell its number counts stay clear, so it always opens an area whatever the neighborhood
+        let safe: Vec<(usize, usize)> = iter::once((centerx, centery)).chain(self.board.neighbors(centerx, centery)).collect();
+        self.start = Some((centerx, centery));
+        if self.board.is_endless() {
+            self.board.seed_mines(self.seed, safe);
+            return;
+        }
+
+        let width = self.board.width();
+        let height = self.board.height();
+        let wanted = width * height * self.difficulty_level.to_owned() as usize / 100; // truncate non integer mine count
+        let mut rng = helpers::seeded_rng(self.seed);
+
+        // on tiny boards the safe area around the first click can leave too little room for every mine
+        let free_cells = width * height - safe.len();
+        let mines = wanted.min(free_cells * self.board.max_mines() as usize);
+        self.flags_available -= (wanted - mines) as i32;
         
         let mut mines_placed = 0;
+        let mut mine_cells = 0; // stacked mines share cells, so this can fall behind mines_placed
         while mines_placed < mines {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-
-            let distance = (x as isize - centerx as isize).abs() + (y as isize - centery as isize).abs(); // Minimum range of forced safe cells to ensure an area is cleared
+            let x = helpers::below(&mut rng, width);
+            let y = helpers::below(&mut rng, height);
 
-            if (self.grid[y][x].mines_seen >= 0 && distance > 2) { // if the mines seen < 0 then it is a mine itself, should not alter
-                self.grid[y][x].mines_seen = -9; // TEMP, negative 9 to see if any mines are being touched by mines seen logic 
-                incriment_neighbors(x, y, &mut self.grid); 
+            let was_mine = self.board.is_mine(x, y);
+            if !safe.contains(&(x, y)) && self.board.add_mine(x, y) {
+                incriment_neighbors(x, y, &mut self.board); 
                 mines_placed += 1;
+                if !was_mine { mine_cells += 1; }
             }
         }
+
+        self.hidden_cells_remaining = width * height - mine_cells;
     }
     
     pub fn reveal_cell(&mut self) {
@@ -150,61 +448,230 @@ impl Game {
             self.first_move_made = true;
         }
 
-        let cell: &mut Cell = &mut self.grid[self.cursor_y][self.cursor_x];
+        self.hint = None;
+        match self.board.state(self.cursor_x, self.cursor_y) {
+            CellState::REVEALED => {
+                self.record(MoveKind::Chord);
+                if self.versus.is_some() { self.versus_reveal(true); }
+                else { self.chord(self.cursor_x, self.cursor_y); }
+            }
+            CellState::FLAGGED => {}
+            _ => {
+                self.record(MoveKind::Reveal);
+                if self.versus.is_some() { self.versus_reveal(false); }
+                else { self.reveal_at(self.cursor_x, self.cursor_y); }
+            }
+        }
+    }
+
+    // a mine only costs the player points here, the board carries on for everyone else
+    fn versus_reveal(&mut self, chord: bool) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let cell = self.board.cell(x, y);
+        let cells_before = self.hidden_cells_remaining;
+        let mut points = 0;
+
+        if chord { self.chord(x, y); } // every flag is a claimed mine, so a chord can never blow one up
+        else if cell.is_mine {
+            self.board.set_state(x, y, CellState::REVEALED);
+            self.flags_available -= cell.mines as i32;
+            points -= VERSUS_MINE_POINTS;
+        }
+        else { self.reveal_at(x, y); }
+
+        points += (cells_before - self.hidden_cells_remaining) as i32;
+        if points == 0 { return; } // nothing happened, e.g. a chord that was not ready, so the turn is not used up
+
+        if let Some(versus) = &mut self.versus {
+            versus.scores[versus.turn] += points;
+            versus.pass_turn();
+        }
+        self.end_versus_if_done();
+    }
+
+    // the game is over once every safe cell is open or every mine is accounted for
+    fn end_versus_if_done(&mut self) {
+        let Some(versus) = &self.versus else { return };
+        if self.hidden_cells_remaining > 0 && self.flags_available > 0 { return; }
+
+        let best = versus.scores.iter().copied().max().unwrap_or(0);
+        let leaders: Vec<usize> = (0..versus.scores.len()).filter(|&player| versus.scores[player] == best).collect();
+        self.game_state = if leaders.len() == 1 { GameState::WINNER(leaders[0]) } else { GameState::DRAW };
+        self.timer.stop(self.clock.now());
+    }
+
+    fn record(&mut self, kind: MoveKind) {
+        if kind != MoveKind::Flag { self.timer.start(self.clock.now()); } // looking at the board before the first reveal is free
+        let time = self.elapsed();
+        self.moves.push(Move { time, kind, x: self.cursor_x, y: self.cursor_y });
+    }
+
+    pub fn cursor(&self) -> (usize, usize) { (self.cursor_x, self.cursor_y) }
+
+    // does what the player would at a cell that is not under the cursor. the cursor goes back afterwards,
+    // unless the game just ended, so the end animation still spreads from the cell that ended it
+    pub fn apply_at(&mut self, x: usize, y: usize, action: Action) -> bool {
+        if self.game_state != GameState::ACTIVE || x >= self.width || y >= self.height { return false; }
+
+        let cursor = self.cursor();
+        self.set_cursor(x, y);
+        match action {
+            Action::Reveal => self.reveal_cell(),
+            Action::Flag => self.toggle_flag(),
+        }
+        if self.game_state == GameState::ACTIVE { self.set_cursor(cursor.0, cursor.1); }
+        true
+    }
+
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            self.cursor_x = x;
+            self.cursor_y = y;
+        }
+    }
+
+    // revealing an already revealed number whose flags are all placed reveals the rest of its neighbors
+    fn chord(&mut self, x: usize, y: usize) {
+        let cell = self.board.cell(x, y);
+        if cell.is_mine { return }
+
+        if self.board.marked_around(x, y) != cell.mines_seen as usize { return }
+
+        for (nx, ny) in self.board.neighbors(x, y) {
+            if self.game_state != GameState::ACTIVE { return }
+            self.reveal_at(nx, ny);
+        }
+    }
+
+    fn reveal_at(&mut self, x: usize, y: usize) {
+        let cell = self.board.cell(x, y);
         
         if cell.cell_state == CellState::REVEALED || cell.cell_state == CellState::FLAGGED { return } // do not allow for flagged cells to be revealed
 
-        // handle game lose, otherwise decrease left by 1
-        cell.cell_state = CellState::REVEALED;
-        if cell.mines_seen >= 0 {
-            if (cell.mines_seen == 0) { 
-                reveal_safe_neighbors(self.cursor_x, self.cursor_y, &mut self.grid);
-                self.update_hidden_cells_remaining();
+        // handle game lose, otherwise decrease left by the number of cells uncovered
+        self.board.set_state(x, y, CellState::REVEALED);
+        if !cell.is_mine {
+            let mut revealed = 1;
+            if cell.mines_seen == 0 { 
+                revealed += reveal_safe_neighbors(x, y, &mut self.board);
             }
-            self.hidden_cells_remaining -= 1;
+            self.hidden_cells_remaining -= revealed;
+            if let Some(endless) = &mut self.endless { endless.revealed += revealed; }
             
-            if self.hidden_cells_remaining <= 0 { 
+            if self.hidden_cells_remaining == 0 { 
                 self.game_state = GameState::SUCSESS; 
-                self.game_end_time = Instant::now();
+                self.timer.stop(self.clock.now());
+            }
+        } else if let Some(endless) = &mut self.endless { // blown up for good, like a lost life
+            endless.mines_hit += 1;
+            self.message = Some(format!("Boom! That mine cost {} points", ENDLESS_MINE_POINTS));
+        } else if let Some(lives) = self.lives.as_mut().filter(|lives| lives.left > 1) {
+            lives.left -= 1;
+            self.flags_available -= cell.mines as i32; // found the hard way, but found
+            self.message = Some(format!("Boom! {} {} left", lives.left, if lives.left == 1 { "life" } else { "lives" }));
+        } else {
+            if let Some(lives) = &mut self.lives {
+                lives.left = 0;
+                self.message = None; // the last boom is not news anymore
             }
-        } else { // if mines seen is negative it is itself a mine, and thus the game is lost 
             self.game_state = GameState::FAILED;
-            self.game_end_time = Instant::now();
+            self.timer.stop(self.clock.now());
         }
     }
 
+    pub fn show_hint(&mut self) {
+        if !self.first_move_made || self.versus.is_some() || self.endless.is_some() { return } // nothing can be deduced from an empty board, no help in versus, and an endless board is too big to search
+        self.hint = solver::hint(&self.board);
+    }
+
     pub fn toggle_flag(&mut self) {
-        let cell: &mut Cell = &mut self.grid[self.cursor_y][self.cursor_x];
+        self.hint = None;
+        let cell = self.board.cell(self.cursor_x, self.cursor_y);
         
         if cell.cell_state == CellState::REVEALED { return } // do not allow for flagging revealed squares
+        if self.versus.is_some() {
+            self.versus_flag();
+            return;
+        }
+        self.record(MoveKind::Flag);
 
-        cell.cell_state = 
-            if cell.cell_state == CellState::HIDDEN { 
-                self.flags_available -= 1;
-                CellState::FLAGGED 
-            } else {
-                self.flags_available += 1;
-                CellState::HIDDEN 
-            }
+        // each press adds one more flag to the cell, going past the most mines a cell can hold clears it
+        if cell.cell_state == CellState::HIDDEN || cell.flags < self.board.max_mines() {
+            self.flags_available -= 1;
+            self.board.set_flags(self.cursor_x, self.cursor_y, cell.flags + 1);
+            self.board.set_state(self.cursor_x, self.cursor_y, CellState::FLAGGED);
+        } else {
+            self.flags_available += cell.flags as i32;
+            self.board.set_flags(self.cursor_x, self.cursor_y, 0);
+            self.board.set_state(self.cursor_x, self.cursor_y, CellState::HIDDEN);
+        }
     }
 
-    
-    pub fn update_hidden_cells_remaining(&mut self) {
-        let mut count = 0;
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid[0].len() {
-                if self.grid[i][j].cell_state == CellState::HIDDEN && self.grid[i][j].mines_seen >= 0 { count += 1; }
+    // a flag is a guess that the cell holds a mine, a right one claims the mine and the turn stays
+    fn versus_flag(&mut self) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let cell = self.board.cell(x, y);
+        if !self.first_move_made || cell.cell_state != CellState::HIDDEN { return } // claimed mines stay claimed
+        self.record(MoveKind::Flag);
+
+        let Some(turn) = self.versus.as_ref().map(|versus| versus.turn) else { return };
+        if cell.is_mine {
+            self.board.set_state(x, y, CellState::FLAGGED);
+            self.board.set_flags(x, y, cell.mines);
+            self.flags_available -= cell.mines as i32;
+            let index = self.board.index(x, y);
+            if let Some(versus) = &mut self.versus {
+                versus.owners[index] = Some(turn);
+                versus.scores[turn] += VERSUS_MINE_POINTS;
             }
+        } else {
+            self.reveal_at(x, y);
+            if let Some(versus) = &mut self.versus { versus.pass_turn(); }
+        }
+        self.end_versus_if_done();
+    }
+}
+
+pub fn number_color(mines_seen: u8) -> Color {
+    match mines_seen {
+        1 => Color::Blue,
+        2 => Color::Rgb(61, 179, 143),
+        3 => Color::LightMagenta,
+        4 => Color::Yellow,
+        5..=8 => Color::Red,
+        _ => Color::White,
+    }
+}
+
+// the text of a cell and its colors on top of whatever the cursor or the end of the game gave it already, for
+// every board that draws cells. mines is how many the cell holds and flags is only there when cells can stack them
+pub fn cell_look(mut style: Style, state: CellState, mines: u8, flags: Option<u8>, mines_seen: u8, compact: bool) -> (String, Style) {
+    let text = match state {
+        CellState::HIDDEN if compact => " ·".to_string(), // without a border a hidden cell needs something to show
+        CellState::HIDDEN => String::new(),
+        CellState::FLAGGED => {
+            style = style.bg(Color::Red);
+            flags.map(|flags| format!(" F{}", flags)).unwrap_or_else(|| " F".to_string())
+        }
+        _ => {
+            if mines > 0 { style = style.bg(Color::Red); }
+            if mines > 1 { format!(" ¤{}", mines) }
+            else if mines == 1 { " ¤".to_string() }
+            else { format!(" {}", mines_seen) }
         }
-        self.hidden_cells_remaining = count + 1;
+    };
+
+    if state == CellState::REVEALED {
+        style = style.fg(if mines > 0 { Color::White } else { number_color(mines_seen) });
     }
+    (text, style)
 }
 
 pub fn render_grid<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game){
     let size =  frame.size();
     
-    let grid_width = CELL_WIDTH * game.width as u16;
-    let grid_height = CELL_HEIGHT * game.height as u16;
+    let (cell_width, cell_height) = game.cell_size();
+    let (grid_width, grid_height) = game.grid_size();
     
     let grid_x = 
         if size.width > grid_width { (size.width - grid_width)/2 } 
@@ -214,107 +681,270 @@ pub fn render_grid<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game){
         if size.height > grid_height { (size.height - grid_height)/2 } 
         else { 0 };
 
-    for y in 0..game.height {
-        for x in 0..game.width {
-            let cell = &mut game.grid[y][x];
-            let cell_x = grid_x + (x as u16 * CELL_WIDTH);
-            let cell_y = grid_y + (y as u16 * CELL_HEIGHT);
+    let (left, top, columns, rows) = game.window();
+    let visible_columns = columns.min((size.width / cell_width) as usize); // no need to walk cells that can never fit on screen
+    let visible_rows = rows.min((size.height / cell_height) as usize);
 
-            if cell_x + CELL_WIDTH > size.width || cell_y + CELL_HEIGHT > size.height { continue; } // if cell is outside of terminal, do not render
+    for y in top..top + visible_rows {
+        for x in left..left + visible_columns {
+            let mut cell = game.board.cell(x, y);
+            let (origin_x, origin_y) = game.board.topology().cell_origin(x - left, y - top, cell_width, cell_height);
+            let cell_x = grid_x + origin_x;
+            let cell_y = grid_y + origin_y;
+
+            if cell_x + cell_width > size.width || cell_y + cell_height > size.height { continue; } // if cell is outside of terminal, do not render
 
             let mut style = Style::default();
-            if game.show_cursor && x == game.cursor_x && y == game.cursor_y { style = style.bg(Color::DarkGray); }
+            let teammate = game.team.as_ref().and_then(|team| (0..team.cursors.len()).find(|&player| player != team.player && team.cursors[player] == Some((x, y))));
+            if game.show_cursor && x == game.cursor_x && y == game.cursor_y {
+                let turn = game.versus.as_ref().filter(|_| game.game_state == GameState::ACTIVE).map(|versus| versus.turn);
+                let color = match (turn, &game.team) { // in versus the cursor shows whose turn it is, in co-op whose cursor it is
+                    (Some(player), _) => PLAYER_COLORS[player],
+                    (None, Some(team)) => Team::color(team.player),
+                    (None, None) => Color::DarkGray,
+                };
+                style = style.bg(color);
+            }
+            else if let Some(player) = teammate.filter(|_| game.game_state == GameState::ACTIVE) {
+                style = style.bg(Team::color(player));
+            }
+            else if let Some(hint) = game.hint.filter(|hint| hint.x == x && hint.y == y) {
+                style = style.bg(if hint.is_mine { Color::Magenta } else { Color::Cyan });
+            }
 
-            let mut cell_text = 
-                if cell.mines_seen < 0 { " ¤".to_string() }
-                else { format!(" {}", cell.mines_seen) };
-            
             match game.game_state {
                 GameState::ACTIVE => {
                     game.game_end_animation_level = 0;
                 }
                 GameState::FAILED => {
-                    if game.game_state != GameState::ACTIVE && Instant::now().duration_since(game.game_time) > END_ANIMATION_DELAY {
-                        game.game_time = Instant::now();
+                    if game.game_state != GameState::ACTIVE && Instant::now().duration_since(game.end_animation_time) > END_ANIMATION_DELAY {
+                        game.end_animation_time = Instant::now();
                         game.game_end_animation_level += 1;
                     }
                     
-                    let distance = (x as isize - game.cursor_x as isize).abs() + (y as isize - game.cursor_y as isize).abs();
+                    let distance = game.board.topology().distance((x, y), (game.cursor_x, game.cursor_y));
         
-                    if (distance as usize) < game.game_end_animation_level { 
+                    if distance < game.game_end_animation_level { 
                         if cell.cell_state == CellState::REVEALED {
                             style = style.bg(Color::Red);
                         } else {
                             cell.cell_state = CellState::REVEALED_AFTER_END;
+                            game.board.set_state(x, y, CellState::REVEALED_AFTER_END);
                             style = style.bg(Color::Red);
                             style = style.fg(Color::Yellow);
                         }
                     }
                 }
-                GameState::SUCSESS => {
-                    if game.game_state != GameState::ACTIVE && Instant::now().duration_since(game.game_time) > END_ANIMATION_DELAY {
-                        game.game_time = Instant::now();
+                GameState::SUCSESS | GameState::WINNER(_) | GameState::DRAW => {
+                    if game.game_state != GameState::ACTIVE && Instant::now().duration_since(game.end_animation_time) > END_ANIMATION_DELAY {
+                        game.end_animation_time = Instant::now();
                         game.game_end_animation_level += 1;
                     }
                     
-                    let distance = (x as isize - game.cursor_x as isize).abs() + (y as isize - game.cursor_y as isize).abs();
+                    let distance = game.board.topology().distance((x, y), (game.cursor_x, game.cursor_y));
         
-                    if (distance as usize) < game.game_end_animation_level { 
+                    if distance < game.game_end_animation_level { 
                         if cell.cell_state == CellState::REVEALED {
                             style = style.bg(Color::Green);
                         } else {
                             cell.cell_state = CellState::REVEALED_AFTER_END;
+                            game.board.set_state(x, y, CellState::REVEALED_AFTER_END);
                             style = style.fg(Color::LightYellow);
                         }
                     }
                 }
             }
 
-            match cell.cell_state {
-                CellState::HIDDEN => { 
-                    cell_text = String::new(); 
-                }
-                CellState::FLAGGED => { 
-                    style = style.bg(Color::Red); 
-                    cell_text = " F".to_string();
-                }
-                CellState::REVEALED => { 
-                    if cell.mines_seen < 0 { style = style.bg(Color::Red); }
-                    cell_text = format!("{}", cell_text);
-                }
-                CellState::REVEALED_AFTER_END => { 
-                    if cell.mines_seen < 0 { style = style.bg(Color::Red); }
-                    cell_text = format!("{}", cell_text);
+            let flags = (game.board.max_mines() > 1).then_some(cell.flags);
+            let (cell_text, mut style) = cell_look(style, cell.cell_state, cell.mines, flags, cell.mines_seen, game.compact);
+            if cell.cell_state == CellState::FLAGGED {
+                if let Some(owner) = game.versus.as_ref().and_then(|versus| versus.owner(game.board.index(x, y))) {
+                    style = style.bg(PLAYER_COLORS[owner]);
                 }
             }
 
-            if cell.cell_state == CellState::REVEALED {
-                match cell.mines_seen {
-
-                    1 => { style = style.fg(Color::Blue); }
-                    2 => { style = style.fg(Color::Rgb(61, 179, 143)); }
-                    3 => { style = style.fg(Color::LightMagenta); }
-                    4 => { style = style.fg(Color::Yellow); }
-                    5 => { style = style.fg(Color::Red); }
-                    6 => { style = style.fg(Color::Red); }
-                    7 => { style = style.fg(Color::Red); }
-                    8 => { style = style.fg(Color::Red); }
+            if let Some(verdict) = game.analysis.as_ref().and_then(|analysis| analysis.verdicts[game.board.index(x, y)]) {
+                style = style.bg(verdict.color()).fg(Color::Black);
+            }
 
-                    _ => {  style = style.fg(Color::White); }
-                }
+            let cell_rect = Rect::new(cell_x, cell_y, cell_width, cell_height);
+            if game.compact {
+                frame.render_widget(Paragraph::new(Text::raw(cell_text)).style(style), cell_rect);
+            } else {
+                frame.render_widget({
+                    Paragraph::new(Text::raw(cell_text))
+                        .block({
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .style(style) 
+                            })
+                        .style(style) 
+                    }, 
+                    cell_rect
+                );
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::topology::{self, Neighborhood};
+
+    // time only moves when the test says so
+    struct FakeClock(Rc<Cell<Instant>>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant { self.0.get() }
+    }
+
+    // one mine in the top left corner. the center is a 1 that opens nothing, the far corner clears the board
+    fn game() -> (Game, Rc<Cell<Instant>>) {
+        let layout = Layout::parse("*..\n...\n...\n").unwrap();
+        let now = Rc::new(Cell::new(Instant::now()));
+        let game = Game::from_layout(topology::build(3, 3, false, false, Neighborhood::Moore), &layout).unwrap()
+            .with_clock(Box::new(FakeClock(now.clone())));
+        (game, now)
+    }
+
+    fn wait(now: &Cell<Instant>, seconds: u64) { now.set(now.get() + Duration::from_secs(seconds)); }
+
+    #[test]
+    fn the_clock_starts_on_the_first_reveal_not_a_flag() {
+        let (mut game, now) = game();
+        wait(&now, 3);
+        game.apply_at(0, 0, Action::Flag);
+        wait(&now, 3);
+        assert_eq!(game.elapsed(), Duration::ZERO);
 
-            frame.render_widget({
-                Paragraph::new(Text::raw(cell_text))
-                    .block({
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .style(style) 
-                        })
-                    .style(style) 
-                }, 
-                Rect::new(cell_x, cell_y, CELL_WIDTH, CELL_HEIGHT)
-            );
+        game.apply_at(1, 1, Action::Reveal);
+        wait(&now, 2);
+        assert!(game.game_state == GameState::ACTIVE);
+        assert_eq!(game.elapsed(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn the_clock_stops_on_a_win() {
+        let (mut game, now) = game();
+        game.apply_at(1, 1, Action::Reveal);
+        wait(&now, 4);
+        game.apply_at(2, 2, Action::Reveal);
+        assert!(game.game_state == GameState::SUCSESS);
+        wait(&now, 10);
+        assert_eq!(game.elapsed(), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn a_board_with_every_safe_cell_open_loads_as_won() {
+        let layout = Layout::parse("*..\n...\n...\n\n#..\n...\n...\n").unwrap();
+        let game = Game::from_layout(topology::build(3, 3, false, false, Neighborhood::Moore), &layout).unwrap();
+        assert!(game.game_state == GameState::SUCSESS);
+        assert_eq!(game.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn the_clock_stops_on_a_loss() {
+        let (mut game, now) = game();
+        game.apply_at(1, 1, Action::Reveal);
+        wait(&now, 4);
+        game.apply_at(0, 0, Action::Reveal);
+        assert!(game.game_state == GameState::FAILED);
+        wait(&now, 10);
+        assert_eq!(game.elapsed(), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn pause_and_resume_leave_the_pause_out() {
+        let (mut game, now) = game();
+        game.apply_at(1, 1, Action::Reveal);
+        wait(&now, 1);
+        game.pause();
+        assert!(game.paused());
+        wait(&now, 30);
+        assert_eq!(game.elapsed(), Duration::from_secs(1));
+
+        game.resume();
+        wait(&now, 2);
+        assert_eq!(game.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn a_pause_before_the_start_is_ignored() {
+        let (mut game, now) = game();
+        game.pause();
+        wait(&now, 5);
+        game.resume();
+        game.apply_at(1, 1, Action::Reveal);
+        wait(&now, 2);
+        assert_eq!(game.elapsed(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn elapsed_never_goes_backwards() {
+        let (mut game, now) = game();
+        let mut last = Duration::ZERO;
+        for second in 0..20 {
+            match second {
+                2 => game.pause(),
+                4 => game.resume(),
+                5 => { game.apply_at(1, 1, Action::Reveal); }
+                8 => game.pause(),
+                11 => game.resume(),
+                15 => { game.apply_at(2, 2, Action::Reveal); }
+                _ => {}
+            }
+            assert!(game.elapsed() >= last, "the clock went back at {}s", second);
+            last = game.elapsed();
+            wait(&now, 1);
         }
+        assert_eq!(last, Duration::from_secs(7));
+    }
+
+    // the mines of a stretch of an endless board far from the start, row by row
+    fn endless_patch(game: &Game, (x, y): (usize, usize)) -> String {
+        (y..y + 3).map(|y| (x..x + 16).map(|x| if game.board().is_mine(x, y) { '*' } else { '.' }).collect::<String>() + "\n").collect()
+    }
+
+    // if this fails every endless seed now grows a different world
+    #[test]
+    fn a_seed_always_grows_the_same_endless_world() {
+        let mut game = Game::endless(Neighborhood::Moore, Difficulty::MEDIUM).with_seed(1);
+        game.reveal_cell();
+        let (x, y) = game.cursor();
+        assert_eq!(endless_patch(&game, (x + 1000, y)), "......**...*.*..\n........*.....*.\n..**..*.........\n");
+    }
+
+    #[test]
+    fn an_endless_mine_costs_points_and_the_run_goes_on() {
+        let mut game = Game::endless(Neighborhood::Moore, Difficulty::HARD).with_seed(7);
+        game.reveal_cell();
+        let revealed = game.endless.as_ref().unwrap().revealed;
+        let (x, y) = game.cursor();
+        let mine = (x + 3..x + 40).find(|&x| game.board().is_mine(x, y) && game.board().state(x, y) == CellState::HIDDEN).unwrap();
+
+        game.apply_at(mine, y, Action::Reveal);
+        assert!(game.game_state == GameState::ACTIVE);
+        let endless = game.endless.as_ref().unwrap();
+        assert_eq!((endless.revealed, endless.mines_hit), (revealed, 1));
+        assert_eq!(endless.score(), revealed as i64 - ENDLESS_MINE_POINTS);
+    }
+
+    #[test]
+    fn an_opening_with_no_end_stops_and_chording_its_edge_carries_on() {
+        let mut game = Game::endless(Neighborhood::Knight, Difficulty::EASY).with_seed(0);
+        game.reveal_cell();
+        let revealed = game.endless.as_ref().unwrap().revealed;
+        assert!((10_000..10_010).contains(&revealed), "revealed {}", revealed); // the last cell it opened from may add its neighbors
+
+        let board = game.board();
+        let (x, y) = game.cursor();
+        let edge = (y - 200..y + 200).flat_map(|y| (x - 200..x + 200).map(move |x| (x, y)))
+            .find(|&(x, y)| board.state(x, y) == CellState::REVEALED && board.mines_seen(x, y) == 0 && board.neighbors(x, y).any(|(nx, ny)| board.state(nx, ny) == CellState::HIDDEN))
+            .unwrap();
+        game.apply_at(edge.0, edge.1, Action::Reveal);
+        assert!(game.endless.as_ref().unwrap().revealed > revealed);
     }
 }
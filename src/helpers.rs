@@ -1,46 +1,82 @@
-use crate::game::{Cell, CellState}; 
-
-pub fn incriment_neighbors(x: usize, y: usize, grid: &mut Vec<Vec<Cell>>) {
-    let max_column = grid.len() as i32;
-    let max_row = grid[0].len() as i32;
-    
-    for dy in -1..=1 {
-        for dx in -1..=1 {
-            if dx == 0 && dy == 0 { continue; } // do not incriment self
-            
-            let nx = x as i32 + dx;
-            let ny = y as i32 + dy;
-            
-            if nx >= 0 && nx < max_row && ny >= 0 && ny < max_column { // is neighbor real? (valid grid indexes)
-                let neighbor = &mut grid[ny as usize][nx as usize];
-                if neighbor.mines_seen < 0 { continue; }
-                neighbor.mines_seen += 1;
-            }
-        }
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::board::Board;
+use crate::game::CellState;
+
+// where a seed puts its mines can never change, dailies and challenge codes depend on it. ChaCha8 is specified
+// down to the bit where StdRng is free to change between rand versions, and the seed expansion is spelled out too
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    ChaCha8Rng::from_seed(bytes)
+}
+
+// a number below n, plain modulo since rand may change how gen_range maps its output
+pub fn below(rng: &mut ChaCha8Rng, n: usize) -> usize { (rng.next_u64() % n as u64) as usize }
+
+pub fn incriment_neighbors(x: usize, y: usize, board: &mut Board) {
+    let neighbors = board.neighbors(x, y);
+    for (nx, ny) in neighbors {
+        board.increment_seen(nx, ny);
     }
 }
 
-pub fn reveal_safe_neighbors(x: usize, y: usize, grid: &mut Vec<Vec<Cell>>) {
-    let max_column = grid.len() as i32;
-    let max_row = grid[0].len() as i32;
-    
-    for dy in -1..=1 {
-        for dx in -1..=1 {
-            if dx == 0 && dy == 0 { continue; } // do not interact with self
-            
-            let nx = x as i32 + dx;
-            let ny = y as i32 + dy;
-            
-            if nx >= 0 && nx < max_row && ny >= 0 && ny < max_column { // is neighbor real? (valid grid indexes)
-                let neighbor = &mut grid[ny as usize][nx as usize];
-                
-                if neighbor.cell_state == CellState::HIDDEN {
-                    neighbor.cell_state = CellState::REVEALED;                    
-                    if neighbor.mines_seen == 0 {
-                        reveal_safe_neighbors(nx as usize, ny as usize, grid); 
-                    }
+// sparse enough mines leave an endless board with openings that never end, so there the flood fill stops after
+// this many cells. the open cells it stopped at can be chorded to carry on
+const ENDLESS_FLOOD_LIMIT: usize = 10_000;
+
+// iterative flood fill so that very large open areas do not overflow the stack, returns the number of cells revealed
+pub fn reveal_safe_neighbors(x: usize, y: usize, board: &mut Board) -> usize {
+    let limit = if board.is_endless() { ENDLESS_FLOOD_LIMIT } else { usize::MAX };
+    let mut revealed = 0;
+    let mut stack = vec![(x, y)];
+
+    while let Some((x, y)) = stack.pop() {
+        if revealed >= limit { break; }
+        let neighbors = board.neighbors(x, y);
+        for (nx, ny) in neighbors {
+            if board.state(nx, ny) == CellState::HIDDEN {
+                board.set_state(nx, ny, CellState::REVEALED);
+                revealed += 1;
+                if board.mines_seen(nx, ny) == 0 {
+                    stack.push((nx, ny));
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    revealed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, Game};
+    use crate::layout;
+    use crate::topology::{self, Neighborhood};
+
+    #[test]
+    fn seeded_rng_is_chacha8_over_the_little_endian_seed() {
+        let mut rng = seeded_rng(0);
+        assert_eq!([rng.next_u64(), rng.next_u64(), rng.next_u64()], [0xd6405f892fef003e, 0xa1a5091fe8b85b7f, 0x3b7f9acec30e842c]);
+    }
+
+    // if this fails every shared challenge code now means a different board
+    #[test]
+    fn a_seed_always_lays_out_the_same_mines() {
+        let game = Game::new(topology::build(9, 9, false, false, Neighborhood::Moore), Difficulty::EASY).with_seed(1).start_at(4, 4);
+        let exported = layout::export(game.board(), true);
+        let mines = exported.split("\n\n").next().unwrap();
+        assert_eq!(mines, "\
+.........
+......*..
+.........
+.........
+......*..
+.*....*.*
+......*..
+*........
+.....*.*.");
+    }
+}
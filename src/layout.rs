@@ -0,0 +1,148 @@
+use std::io;
+
+use crate::board::Board;
+use crate::cli::MAX_BOARD_SIDE;
+use crate::game::CellState;
+
+// plain text boards, one character per cell.
+//
+// the first block of lines is the mine layer: '.' safe, '*' a mine, '2'-'9' a cell holding that many mines
+// and '?' for a mine layer that was exported before the game ended. an optional second block, after a
+// blank line, is the revealed state layer: '#' hidden, '.' revealed, 'F' flagged, '1'-'9' a flag count
+#[derive(Clone)]
+pub struct Layout {
+    pub width: usize,
+    pub height: usize,
+    pub mines: Vec<u8>, // row major, like the board
+    pub states: Option<Vec<(CellState, u8)>>, // cell state and flag count
+}
+
+impl Layout {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let mut blocks = text.split("\n\n").map(|block| block.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect::<Vec<_>>()).filter(|block| !block.is_empty());
+
+        let mine_rows = blocks.next().ok_or_else(|| invalid("the board file is empty"))?;
+        let height = mine_rows.len();
+        let width = mine_rows[0].chars().count();
+        if width > MAX_BOARD_SIDE || height > MAX_BOARD_SIDE { return Err(invalid(&format!("the board is bigger than {}x{}", MAX_BOARD_SIDE, MAX_BOARD_SIDE))); }
+
+        let mut mines = Vec::with_capacity(width * height);
+        for (y, row) in mine_rows.iter().enumerate() {
+            if row.chars().count() != width { return Err(invalid(&format!("mine layer row {} is not {} cells wide", y + 1, width))); }
+            for c in row.chars() {
+                mines.push(match c {
+                    '.' => 0,
+                    '*' => 1,
+                    '2'..='9' => c as u8 - b'0',
+                    '?' => return Err(invalid("the mine layer has unknown cells, it was exported before the game ended")),
+                    _ => return Err(invalid(&format!("unexpected '{}' in the mine layer", c))),
+                });
+            }
+        }
+
+        let states = match blocks.next() {
+            None => None,
+            Some(state_rows) => {
+                if state_rows.len() != height { return Err(invalid("the state layer does not have the same number of rows as the mine layer")); }
+
+                let mut states = Vec::with_capacity(width * height);
+                for (y, row) in state_rows.iter().enumerate() {
+                    if row.chars().count() != width { return Err(invalid(&format!("state layer row {} is not {} cells wide", y + 1, width))); }
+                    for c in row.chars() {
+                        states.push(match c {
+                            '#' => (CellState::HIDDEN, 0),
+                            '.' => (CellState::REVEALED, 0),
+                            'F' => (CellState::FLAGGED, 1),
+                            '1'..='9' => (CellState::FLAGGED, c as u8 - b'0'),
+                            _ => return Err(invalid(&format!("unexpected '{}' in the state layer", c))),
+                        });
+                    }
+                }
+                Some(states)
+            }
+        };
+
+        Ok(Layout { width, height, mines, states })
+    }
+
+    pub fn max_mines(&self) -> u8 {
+        self.mines.iter().copied().max().unwrap_or(0).max(1)
+    }
+}
+
+// the mine positions are only written out once reveal_mines is set, i.e. after the game is over
+pub fn export(board: &Board, reveal_mines: bool) -> String {
+    let mut text = String::new();
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let cell = board.cell(x, y);
+            text.push(match cell.mines {
+                _ if !reveal_mines => '?',
+                0 => '.',
+                1 => '*',
+                mines => (b'0' + mines) as char,
+            });
+        }
+        text.push('\n');
+    }
+
+    text.push('\n');
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let cell = board.cell(x, y);
+            text.push(match cell.cell_state {
+                CellState::REVEALED => '.',
+                CellState::FLAGGED if board.max_mines() > 1 => (b'0' + cell.flags) as char,
+                CellState::FLAGGED => 'F',
+                _ => '#', // cells uncovered by the end animation were never actually revealed
+            });
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::topology::{self, Neighborhood};
+
+    fn round_trip(text: &str) -> String {
+        let layout = Layout::parse(text).unwrap();
+        let topology = topology::build(layout.width, layout.height, false, false, Neighborhood::Moore);
+        export(Game::from_layout(topology, &layout).unwrap().board(), true)
+    }
+
+    #[test]
+    fn a_board_exports_back_to_the_file_it_was_loaded_from() {
+        let text = "*..*\n....\n.*..\n\n#..F\n....\n##..\n";
+        assert_eq!(round_trip(text), text);
+    }
+
+    #[test]
+    fn stacked_mines_and_flag_counts_survive_the_trip() {
+        let text = "2..\n.*.\n..3\n\n2..\n...\n..1\n";
+        assert_eq!(round_trip(text), text);
+    }
+
+    #[test]
+    fn a_board_without_a_state_layer_starts_hidden() {
+        assert_eq!(round_trip("*.\r\n..\r\n"), "*.\n..\n\n##\n##\n");
+    }
+
+    #[test]
+    fn broken_files_are_turned_away() {
+        for text in ["", "*..\n..\n", "*.x\n...\n", "??\n??\n", "*.\n..\n\n##\n", "*.\n..\n\n#\n##\n", "*.\n..\n\n#x\n##\n"] {
+            assert!(Layout::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -1,182 +1,866 @@
+mod analysis;
+mod bench;
+mod board;
+mod cli;
+mod clock;
+mod coop;
+mod crash;
+mod drill;
 mod game;
 mod helpers;
+mod layout;
+mod net;
+mod preset;
+mod race;
+mod replay;
+mod share;
+mod solver;
+mod spectate;
+mod stats;
+mod status;
+mod telnet;
+mod topology;
+mod tutorial;
 
-use crate::game::{Game, GameState, Difficulty};
-use std::{env, io, time::{Duration, Instant}};
+use crate::cli::{Cli, Command, DrillArgs, EndlessArgs, PlayArgs, ReplayArgs, ServeArgs, SolveArgs, TutorialArgs};
+use crate::game::{Action, Game, GameState, Difficulty};
+use crate::layout::Layout;
+use crate::preset::Preset;
+use crate::coop::Coop;
+use crate::drill::Drill;
+use crate::net::Session;
+use crate::race::Race;
+use crate::replay::{ReplayClock, ReplayEvent};
+use crate::share::Challenge;
+use crate::spectate::{Publisher, Watcher};
+use crate::status::{StatusBar, StatusItem};
+use crate::telnet::{Telnet, TelnetEvent};
+use crate::topology::{Direction, Neighborhood, Topology};
+use crate::tutorial::Tutorial;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use std::{cell::Cell, fs, io, net::{TcpListener, TcpStream}, panic::{self, AssertUnwindSafe}, process, rc::Rc, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 use crossterm::{  
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    cursor::{Hide, Show},
+    event::{self, EnableFocusChange, EnableMouseCapture, Event, KeyCode},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, size},
+    terminal::{enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, size},
 };
 use tui::{
-    backend::CrosstermBackend, layout::Rect, prelude::Backend, style::{Color, Style}, text::Text, widgets::Paragraph, Terminal
+    backend::CrosstermBackend, layout::Rect, prelude::Backend, style::{Color, Style}, text::{Line, Span, Text}, widgets::{Paragraph, Wrap}, Terminal, TerminalOptions, Viewport
 };
 
+const MIN_TERMINAL_WIDTH: u16 = 24; // the flag count and the quit hint still need to fit
+
 fn main() -> Result<(), io::Error> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Play(args)) => play(args),
+        Some(Command::Stats(_)) => {
+            print!("{}", stats::summary(&stats::load()));
+            Ok(())
+        },
+        Some(Command::Replay(args)) => watch_replay(args),
+        Some(Command::Bench(_)) => {
+            bench::run();
+            Ok(())
+        },
+        Some(Command::Solve(args)) => solve(args),
+        Some(Command::Serve(args)) => serve(args),
+        Some(Command::Tutorial(args)) => tutorial(args),
+        Some(Command::Drill(args)) => run_drill(args),
+        Some(Command::Endless(args)) => endless(args),
+        None => play(cli.play),
+    }
+}
+
+fn play(args: PlayArgs) -> io::Result<()> {
+    if let Some(address) = &args.watch { return watch_game(address, args.compact, args.hide_timer, args.status); }
 
     let (term_width, term_height) = size()?;
-    let mut width = ((term_width as usize - 10) / game::CELL_WIDTH as usize).max(5).min(30); // allow some space for borders and UI elements
-    let mut height = ((term_height as usize - 5) / game::CELL_HEIGHT as usize).max(5).min(20);
-    let mut difficulty = Difficulty::MEDIUM;
-    let mut hide_timer = false;
+    let mut width = args.width
+        .or(args.preset.map(|preset| preset.width))
+        .unwrap_or_else(|| ((term_width as usize).saturating_sub(10) / game::CELL_WIDTH as usize).clamp(5, 30)); // allow some space for borders and UI elements
+    let mut height = args.height
+        .or(args.preset.map(|preset| preset.height))
+        .unwrap_or_else(|| ((term_height as usize).saturating_sub(5) / game::CELL_HEIGHT as usize).clamp(5, 20));
+    let mut difficulty = args.difficulty.clone()
+        .or(args.preset.map(|preset| preset.difficulty.clone()))
+        .unwrap_or(Difficulty::MEDIUM);
+    let (mut wrap, mut hex, mut neighborhood, mut max_mines) = (args.wrap, args.hex, args.neighborhood, args.max_mines);
+    let board_file = args.board;
     
-    let args: Vec<String> = env::args().collect();
-    for i in 1..args.len() {
-        match args[i].as_str() {
-            "--width" | "-w" => {
-                if i + 1 < args.len() {
-                    if let Ok(w) = args[i + 1].parse::<usize>() {
-                        width = w;
-                    }
-                }
-            },
-            "--height" | "-h" => {
-                if i + 1 < args.len() {
-                    if let Ok(h) = args[i + 1].parse::<usize>() {
-                        height = h;
-                    }
-                }
-            },
-            "--difficulty" | "-d" => {
-                if i + 1 < args.len() {
-                    match args[i + 1].to_lowercase().as_str() {
-                        "easy" | "e" => difficulty = Difficulty::EASY,
-                        "medium" | "m" => difficulty = Difficulty::MEDIUM,
-                        "hard" | "h" => difficulty = Difficulty::HARD,
-                        _ => {}
-                    }
-                }
-            },
-            "--hide-timer" | "-t" => {
-                hide_timer = true;
-            },
-            "--help" => {
-                println!("Termsweeper - A terminal-based Minesweeper game
-                    \n\nUsage: termsweeper [OPTIONS]
-                    \n\nOptions:
-                    \n  -w, --width WIDTH        Set grid width (with current terminal size: {})
-                    \n  -h, --height HEIGHT      Set grid height (with current terminal size: {})
-                    \n  -d, --difficulty LEVEL   Set difficulty level: easy, medium, hard (default: medium)
-                    \n  -t, --hide-timer         Set the visability of the game clock (default: visable)
-                    \n  --help                   Gives you all of this very helpful information!\n", 
-                    width, height);
-                return Ok(());
-            },
-            _ => {}
+    let mut message = None;
+    let mut official_daily = false;
+    let today = stats::today();
+
+    let mut sessions: Vec<Box<dyn Session>> = Vec::new();
+    let mut challenge = args.challenge;
+    let mut team_player = if args.coop { Some(0) } else { None }; // the host is always player 0
+    if let Some(address) = &args.join { // the host decides the board and the mode, the board arrives as a challenge code
+        match net::connect(address) {
+            Ok((host, welcome)) => {
+                sessions.push(if welcome.coop { Box::new(Coop::joined(host, welcome.player)) } else { Box::new(Race::joined(host, welcome.player)) });
+                message = Some(
+                    if welcome.coop { format!("Co-op as Player {}, one mine ends it for everyone", welcome.player + 1) }
+                    else { format!("Racing as Player {}", welcome.player + 1) }
+                );
+                team_player = Some(welcome.player).filter(|_| welcome.coop);
+                challenge = Some(welcome.setup);
+            }
+            Err(err) => {
+                eprintln!("Could not join the game at {}: {}", address, err);
+                process::exit(1);
+            }
         }
     }
+
+    if args.daily { // a fixed classic setup, clap turns away anything that would change the board
+        let preset = preset::find(preset::DAILY_PRESET).expect("the daily preset exists");
+        width = preset.width;
+        height = preset.height;
+        difficulty = preset.difficulty.clone();
+
+        official_daily = !stats::played_daily(&stats::load(), today);
+        message = Some(
+            if official_daily { format!("Daily challenge {}", today.format("%Y-%m-%d")) }
+            else { "You already played today's daily, this attempt is unofficial".to_string() }
+        );
+    } else if let Some(code) = &challenge { // the code carries the whole setup, like a daily does
+        (width, height, difficulty) = (code.width, code.height, code.difficulty.clone());
+        (wrap, hex, neighborhood, max_mines) = (code.wrap, code.hex, code.neighborhood, code.max_mines);
+        if sessions.is_empty() { message = Some(format!("Challenge {}", code.encode())); }
+    }
+
+    let layout = board_file.map(|path| load_board(&path));
+    if let Some(layout) = &layout { // the board file decides the size
+        width = layout.width;
+        height = layout.height;
+    }
+    if hex && wrap && height % 2 == 1 && layout.is_none() { // offset rows only line up across the seam with an even row count
+        if args.height.is_some() || args.preset.is_some() {
+            Cli::command().error(ErrorKind::ValueValidation, "a wrapped hex board needs an even number of rows (-h)").exit();
+        }
+        height -= 1; // the height that fits the terminal is ours to pick
+    }
+
+    let topology = topology::build(width, height, hex, wrap, neighborhood);
+    let mut app = match &layout {
+        Some(layout) => game_from_layout(topology, layout),
+        None if args.daily => Game::new(topology, difficulty.clone()).with_seed(stats::daily_seed(today)).start_at_center(),
+        None => match &challenge {
+            Some(code) => Game::new(topology, difficulty.clone()).with_max_mines(max_mines).with_seed(code.seed).start_at(code.start.0, code.start.1),
+            None if args.host.is_some() => Game::new(topology, difficulty.clone()).with_max_mines(max_mines).start_at_center(), // the seed alone has to decide the board
+            None => Game::new(topology, difficulty.clone()).with_max_mines(max_mines),
+        },
+    };
+    if args.versus {
+        app = app.with_versus();
+        message = Some(format!("Versus: a flagged mine scores {} and keeps the turn, a reveal scores 1 per cell", game::VERSUS_MINE_POINTS));
+    }
+    if let Some(player) = team_player { app = app.with_team(player); }
+    if let Some(lives) = args.lives { app = app.with_lives(lives); }
+    app.compact = args.compact;
     
+    let setup = Challenge { width, height, difficulty: difficulty.clone(), hex, wrap, neighborhood, max_mines, start: app.start.unwrap_or((0, 0)), seed: app.seed };
+    if let Some(port) = args.host {
+        let listener = net::listen("0.0.0.0", port).and_then(|listener| Ok((listener.local_addr()?.port(), listener)));
+        match listener {
+            Ok((port, listener)) => {
+                let mode = if args.coop { "co-op game" } else { "race" };
+                message = Some(format!("Hosting a {} on port {}, others join with --join <this address>:{}", mode, port, port));
+                sessions.push(if args.coop { Box::new(Coop::host(listener, setup.encode())) } else { Box::new(Race::host(listener, setup.encode())) });
+            }
+            Err(err) => {
+                eprintln!("Could not host on port {}: {}", port, err);
+                process::exit(1);
+            }
+        }
+    }
+    let record_stats = layout.is_none() && !args.versus && sessions.is_empty(); // hand written boards, versus games and network games are not your own games
+    if let Some(port) = args.publish { // only shows the game, so unlike the modes above it leaves the stats alone
+        match Publisher::new(port) {
+            Ok(publisher) => {
+                if message.is_none() { message = Some(format!("Published, watch with --watch 127.0.0.1:{}", publisher.port())); }
+                sessions.push(Box::new(publisher));
+            }
+            Err(err) => {
+                eprintln!("Could not publish the game on port {}: {}", port, err);
+                process::exit(1);
+            }
+        }
+    }
+    app.message = message;
+
+    let category =
+        if args.daily { stats::DAILY_CATEGORY.to_string() }
+        else { category_name(width, height, &difficulty, app.board().topology(), max_mines, args.lives) };
+    let hide_timer = args.hide_timer;
+    let status = StatusBar { preset: category.clone(), ..args.status };
+
+    // the game is borrowed rather than moved in, so it is still around to write a crash report from
+    let res = with_terminal(|terminal| Ok(panic::catch_unwind(AssertUnwindSafe(|| run_app(terminal, &mut app, hide_timer, &status, &mut sessions)))));
+    let res = match res {
+        Ok(Err(payload)) => {
+            let challenge = app.start.filter(|_| record_stats).map(|start| Challenge { start, ..setup.clone() }.encode());
+            match crash::write_report(&app, challenge, &crash::panic_message(payload.as_ref())) {
+                Ok(path) => eprintln!("Termsweeper crashed, a report with the seed and every move was saved to {}", path.display()),
+                Err(err) => eprintln!("Termsweeper crashed and could not save a crash report: {}", err),
+            }
+            panic::resume_unwind(payload);
+        }
+        Ok(Ok(res)) => res,
+        Err(err) => Err(err),
+    };
+
+    match res {
+        Err(err) => println!("Error: {:?}", err),
+        Ok(()) if record_stats => {
+            if args.share || args.share_file.is_some() {
+                share_game(&app, &category, setup, args.share_file, args.ascii);
+            }
+            record_game(&app, category, args.daily, official_daily, today);
+        },
+        Ok(()) => {}
+    }
+    
+    Ok(())
+}
+
+fn watch_replay(args: ReplayArgs) -> io::Result<()> {
+    let replay = match fs::read_to_string(&args.file).and_then(|text| replay::parse(&text)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Could not load replay {}: {}", args.file, err);
+            process::exit(1);
+        }
+    };
+
+    let topology = topology::build(replay.layout.width, replay.layout.height, false, false, Neighborhood::Moore);
+    let mut app = game_from_layout(topology, &replay.layout);
+    app.message = Some(format!("Replay of {}, press 'q' to stop", replay.player));
+    app.compact = args.compact;
+
+    let status = StatusBar { preset: "replay".to_string(), ..args.status };
+    if let Err(err) = with_terminal(|terminal| run_replay(terminal, app, replay.events, args.hide_timer, &status)) {
+        println!("Error: {:?}", err);
+    }
+    Ok(())
+}
+
+fn watch_game(address: &str, compact: bool, hide_timer: bool, status: StatusBar) -> io::Result<()> {
+    let watcher = match Watcher::connect(address) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            eprintln!("Could not watch the game at {}: {}", address, err);
+            process::exit(1);
+        }
+    };
+
+    let status = StatusBar { preset: "watching".to_string(), ..status };
+    if let Err(err) = with_terminal(|terminal| run_watch(terminal, watcher, address, compact, hide_timer, &status)) {
+        println!("Error: {:?}", err);
+    }
+    Ok(())
+}
+
+fn solve(args: SolveArgs) -> io::Result<()> {
+    let layout = load_board(&args.file);
+    let topology = topology::build(layout.width, layout.height, args.hex, args.wrap, args.neighborhood);
+    let game = game_from_layout(topology, &layout);
+
+    print!("{}", solver::report(game.board()));
+    Ok(())
+}
+
+// the lessons check every move themselves, so the game is run like any other with the tutorial as its session
+fn tutorial(args: TutorialArgs) -> io::Result<()> {
+    let tutorial = Tutorial::new(args.lesson as usize - 1);
+    let mut app = tutorial.game();
+    app.compact = args.compact;
+
+    let status = StatusBar { items: Vec::new(), preset: "tutorial".to_string() };
+    let mut sessions: Vec<Box<dyn Session>> = vec![Box::new(tutorial)];
+    if let Err(err) = with_terminal(|terminal| run_app(terminal, &mut app, true, &status, &mut sessions)) {
+        println!("Error: {:?}", err);
+    }
+    Ok(())
+}
+
+fn run_drill(args: DrillArgs) -> io::Result<()> {
+    let score = Rc::new(Cell::new((0, 0)));
+    let grade = args.difficulty.name();
+    let Some((drill, mut app)) = Drill::start(args.difficulty, score.clone()) else {
+        eprintln!("Could not find a {} drill puzzle, please try again", grade);
+        process::exit(1);
+    };
+    app.compact = args.compact;
+
+    let status = StatusBar { items: Vec::new(), preset: "drill".to_string() };
+    let mut sessions: Vec<Box<dyn Session>> = vec![Box::new(drill)];
+    if let Err(err) = with_terminal(|terminal| run_app(terminal, &mut app, true, &status, &mut sessions)) {
+        println!("Error: {:?}", err);
+    }
+
+    let (right, answered) = score.get();
+    if let Some(percent) = (right * 100).checked_div(answered) { println!("Drill ({}): {} of {} right, {}% accuracy", grade, right, answered, percent); }
+    Ok(())
+}
+
+fn endless(args: EndlessArgs) -> io::Result<()> {
+    let mut app = Game::endless(args.neighborhood, args.difficulty).with_seed(args.seed.unwrap_or_else(rand::random));
+    app.compact = args.compact;
+    app.message = Some("Endless: every cell scores a point, a mine costs points, 'q' ends the run".to_string());
+
+    let status = StatusBar { items: vec![StatusItem::ClicksPerSecond, StatusItem::Preset], preset: "endless".to_string() }; // the rest count the whole board
+    if let Err(err) = with_terminal(|terminal| run_app(terminal, &mut app, false, &status, &mut [])) {
+        println!("Error: {:?}", err);
+    }
+    if let Some(endless) = &app.endless {
+        println!("Endless run: {} points, {} cells revealed, {} mines hit (seed {})", endless.score(), endless.revealed, endless.mines_hit, app.seed);
+    }
+    Ok(())
+}
+
+// one thread and one game per connection, nothing is shared between them
+fn serve(args: ServeArgs) -> io::Result<()> {
+    let listener = match TcpListener::bind(("0.0.0.0", args.port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Could not listen on port {}: {}", args.port, err);
+            process::exit(1);
+        }
+    };
+    println!("Serving {} games on port {}, connect with: telnet <this address> {}", args.preset.name, args.port, args.port);
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let address = stream.peer_addr().map(|address| address.to_string()).unwrap_or_else(|_| "unknown".to_string());
+        let preset = args.preset;
+        let status = StatusBar { preset: preset.name.to_string(), ..args.status.clone() };
+        let compact = args.compact;
+
+        println!("{} connected", address);
+        thread::spawn(move || {
+            match run_remote(stream, preset, compact, &status) {
+                Ok(()) => println!("{} left", address),
+                Err(err) => println!("{} dropped: {}", address, err),
+            }
+        });
+    }
+    Ok(())
+}
+
+fn load_board(path: &str) -> Layout {
+    match fs::read_to_string(path).and_then(|text| Layout::parse(&text)) {
+        Ok(layout) => layout,
+        Err(err) => {
+            eprintln!("Could not load board {}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn game_from_layout(topology: Box<dyn Topology>, layout: &Layout) -> Game {
+    match Game::from_layout(topology, layout) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("Could not load board: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// raw mode and the alternate screen only last as long as run does, even if it panics
+fn with_terminal<T>(run: impl FnOnce(&mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<T>) -> io::Result<T> {
+    crash::install_panic_hook();
+    let _guard = crash::TerminalGuard;
+
     enable_raw_mode()?;
     let mut stdout: io::Stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
     let backend: CrosstermBackend<io::Stdout> = CrosstermBackend::new(stdout);
     let mut terminal: Terminal<CrosstermBackend<io::Stdout>> = Terminal::new(backend)?;
-    
-    let app = Game::new(width, height, difficulty);
-    let res = run_app(&mut terminal, app, hide_timer);
-    
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-    
-    if let Err(err) = res {
-        println!("Error: {:?}", err);
+
+    run(&mut terminal)
+}
+
+// presets keep their own name, anything else is lumped together by its rules
+// games with spare lives are filed apart from the rest, their times are not comparable
+fn category_name(width: usize, height: usize, difficulty: &Difficulty, topology: &dyn Topology, max_mines: u8, lives: Option<u32>) -> String {
+    let percent = difficulty.clone() as usize;
+    let preset = preset::PRESETS.iter().find(|preset| preset.width == width && preset.height == height && preset.difficulty.clone() as usize == percent);
+
+    let name = match preset {
+        Some(preset) if topology.is_classic() && max_mines == 1 => preset.name.to_string(),
+        _ if topology.is_classic() && max_mines == 1 => "custom".to_string(),
+        _ => format!("custom {}", topology.name()),
+    };
+    match lives {
+        Some(lives) => format!("{}, {} lives", name, lives),
+        None => name,
     }
-    
-    Ok(())
 }
 
-fn run_app<B: tui::backend::Backend>(terminal: &mut Terminal<B>, mut game: Game, hide_timer: bool) -> io::Result<()> {
-    let mut key_processed: bool;
+fn record_game(game: &Game, category: String, daily: bool, official_daily: bool, today: chrono::NaiveDate) {
+    // a daily counts as soon as it is started, so it cannot be peeked at and restarted
+    let finished = game.game_state != GameState::ACTIVE;
+    if !(finished || (daily && game.started())) { return; }
+
+    let record = stats::Record {
+        category,
+        date: today,
+        seed: game.seed,
+        won: game.game_state == GameState::SUCSESS,
+        time: game.elapsed(),
+        three_bv: game.board().three_bv(),
+    };
+
+    if daily {
+        println!("Daily {}: {}", today.format("%Y-%m-%d"), stats::describe(&record));
+        if !official_daily { println!("(unofficial, you already played today's daily)"); }
+    }
+
+    if !daily || official_daily {
+        if let Err(err) = stats::append(&record) {
+            println!("Could not save stats: {}", err);
+        }
+    }
+
+    if daily { print!("{}", stats::daily_summary(&stats::load(), today)); }
+}
+
+fn share_game(game: &Game, label: &str, setup: Challenge, path: Option<String>, ascii: bool) {
+    let Some(start) = game.start else { return }; // no mines were ever laid, so there is nothing to share
+    if game.game_state == GameState::ACTIVE { return; }
+
+    let text = share::summary(game, label, &Challenge { start, ..setup }, ascii);
+    match path {
+        Some(path) => match fs::write(&path, text) {
+            Ok(()) => println!("Result saved to {}", path),
+            Err(err) => println!("Could not save result: {}", err),
+        },
+        None => print!("{}", text),
+    }
+}
+
+fn run_app<B: tui::backend::Backend>(terminal: &mut Terminal<B>, game: &mut Game, hide_timer: bool, status: &StatusBar, sessions: &mut [Box<dyn Session>]) -> io::Result<()> {
     let mut last_key_time = Instant::now();
     let debounce_duration = Duration::from_millis(125);
+    let mut paused_by_focus = false; // only a pause the focus caused is undone when the focus comes back
     
     loop {
-        terminal.draw(|f| ui(f, &mut game, hide_timer))?;
+        for session in sessions.iter_mut() { session.poll(game); } // the others keep playing while this game is paused or over
+        terminal.draw(|f| ui(f, game, hide_timer, status, sessions))?;
 
-        key_processed = true;
-        
         if event::poll(Duration::from_millis(10))? {
-            if let Event::Key(key) = event::read()? {
-                    let current_time = Instant::now();
+            let event = event::read()?;
+            match event {
+                Event::FocusLost if !game.paused() => {
+                    game.pause();
+                    paused_by_focus = game.paused();
+                }
+                Event::FocusGained if paused_by_focus => {
+                    game.resume();
+                    paused_by_focus = false;
+                }
+                Event::Resize(_, _) => { terminal.autoresize()?; }
+                _ => {}
+            }
+
+            if let Event::Key(key) = event {
+                let current_time = Instant::now();
 
                 if current_time.duration_since(last_key_time) < debounce_duration { continue; }
-                
-                if game.game_state == GameState::ACTIVE {
-                    match key.code {
-                        KeyCode::Char('q') => { return Ok(()); }
-                        KeyCode::Esc => { return Ok(()); }
-                    
-                        KeyCode::Up => { game.move_cursor(key.code); }
-                        KeyCode::Down => { game.move_cursor(key.code); }
-                        KeyCode::Left => { game.move_cursor(key.code); }
-                        KeyCode::Right => { game.move_cursor(key.code); }
-                    
-                        KeyCode::Char('k') => { game.move_cursor(KeyCode::Up); }
-                        KeyCode::Char('j') => { game.move_cursor(KeyCode::Down); }
-                        KeyCode::Char('h') => { game.move_cursor(KeyCode::Left); }
-                        KeyCode::Char('l') => { game.move_cursor(KeyCode::Right); }
-                    
-                        KeyCode::Char('w') => { game.move_cursor(KeyCode::Up); }
-                        KeyCode::Char('s') => { game.move_cursor(KeyCode::Down); }
-                        KeyCode::Char('a') => { game.move_cursor(KeyCode::Left); }
-                        KeyCode::Char('d') => { game.move_cursor(KeyCode::Right); }
-                    
-                        KeyCode::Char(' ') => { game.toggle_flag(); }
-                        KeyCode::Char('f') => { game.toggle_flag(); }
-                        KeyCode::Enter => { game.reveal_cell(); }
-                        KeyCode::Char('e') => { game.reveal_cell(); }
-                    
-                        _ => { key_processed = false; }
-                    }
-                
-                    if key_processed { last_key_time = current_time; }
-                } else {
-                    match key.code {
-                        KeyCode::Char('q') => { return Ok(()); }
-                        KeyCode::Esc => { return Ok(()); }
-
-                        KeyCode::Up => { continue; } // keep the movement keys from making the user quit
-                        KeyCode::Down => { continue; } // as to not exit the application without them fully
-                        KeyCode::Left => { continue; } // realizing that they had lost
-                        KeyCode::Right => { continue; }
-                    
-                        KeyCode::Char('k') => { continue; }
-                        KeyCode::Char('j') => { continue; }
-                        KeyCode::Char('h') => { continue; }
-                        KeyCode::Char('l') => { continue; }
-                    
-                        KeyCode::Char('w') => { continue; }
-                        KeyCode::Char('s') => { continue; }
-                        KeyCode::Char('a') => { continue; }
-                        KeyCode::Char('d') => { continue; }
-
-                        _ => { return Ok(()); } // any other key should allow the user to quit
+
+                let fits = fits(game, status, sessions, terminal.size()?);
+                match handle_key(game, key.code, fits, sessions, true) {
+                    KeyOutcome::Quit => { return Ok(()); }
+                    KeyOutcome::Handled => {
+                        last_key_time = current_time;
+                        if !game.paused() { paused_by_focus = false; }
                     }
+                    KeyOutcome::Ignored => {}
+                }
+            }
+        }
+    }
+}
+
+enum KeyOutcome {
+    Quit,
+    Handled,
+    Ignored,
+}
+
+// what a key does on the play screen, for the local terminal and remote ones alike. exports write files
+// on this machine, so they are left out for remote players
+fn handle_key(game: &mut Game, code: KeyCode, fits: bool, sessions: &mut [Box<dyn Session>], exports: bool) -> KeyOutcome {
+    if code == KeyCode::Char('c') { // works on every screen, it is how a too small terminal gets out
+        game.compact = !game.compact;
+        return KeyOutcome::Handled;
+    }
+    
+    if !fits {
+        match code {
+            KeyCode::Char('q') => KeyOutcome::Quit,
+            KeyCode::Esc => KeyOutcome::Quit,
+            _ => KeyOutcome::Ignored, // the board cannot be seen, so it cannot be played
+        }
+    } else if game.paused() {
+        match code {
+            KeyCode::Char('q') => KeyOutcome::Quit,
+            KeyCode::Esc => KeyOutcome::Quit,
+            KeyCode::Char('p') => {
+                game.resume();
+                KeyOutcome::Handled
+            }
+            _ => KeyOutcome::Ignored, // the board is hidden, nothing else may touch it
+        }
+    } else if game.game_state == GameState::ACTIVE {
+        match code {
+            KeyCode::Char('q') => { return KeyOutcome::Quit; }
+            KeyCode::Esc => { return KeyOutcome::Quit; }
+        
+            KeyCode::Up => { game.move_cursor(Direction::Up); }
+            KeyCode::Down => { game.move_cursor(Direction::Down); }
+            KeyCode::Left => { game.move_cursor(Direction::Left); }
+            KeyCode::Right => { game.move_cursor(Direction::Right); }
+        
+            KeyCode::Char('k') => { game.move_cursor(Direction::Up); }
+            KeyCode::Char('j') => { game.move_cursor(Direction::Down); }
+            KeyCode::Char('h') => { game.move_cursor(Direction::Left); }
+            KeyCode::Char('l') => { game.move_cursor(Direction::Right); }
+        
+            KeyCode::Char('w') => { game.move_cursor(Direction::Up); }
+            KeyCode::Char('s') => { game.move_cursor(Direction::Down); }
+            KeyCode::Char('a') => { game.move_cursor(Direction::Left); }
+            KeyCode::Char('d') => { game.move_cursor(Direction::Right); }
+
+            KeyCode::Char('y') => { game.move_cursor(Direction::UpLeft); }
+            KeyCode::Char('u') => { game.move_cursor(Direction::UpRight); }
+            KeyCode::Char('b') => { game.move_cursor(Direction::DownLeft); }
+            KeyCode::Char('n') => { game.move_cursor(Direction::DownRight); }
+        
+            KeyCode::Char(' ') => { act(game, sessions, Action::Flag); }
+            KeyCode::Char('f') => { act(game, sessions, Action::Flag); }
+            KeyCode::Enter => { act(game, sessions, Action::Reveal); }
+            KeyCode::Char('e') => { act(game, sessions, Action::Reveal); }
+            KeyCode::Char('?') => { game.show_hint(); }
+            KeyCode::Char('x') if exports => { export_board(game); }
+            KeyCode::Char('p') => { game.pause(); }
+        
+            _ => { return KeyOutcome::Ignored; }
+        }
+        KeyOutcome::Handled
+    } else {
+        match code {
+            KeyCode::Char('q') => KeyOutcome::Quit,
+            KeyCode::Esc => KeyOutcome::Quit,
+
+            KeyCode::Up => KeyOutcome::Ignored, // keep the movement keys from making the user quit
+            KeyCode::Down => KeyOutcome::Ignored, // as to not exit the application without them fully
+            KeyCode::Left => KeyOutcome::Ignored, // realizing that they had lost
+            KeyCode::Right => KeyOutcome::Ignored,
+        
+            KeyCode::Char('k') => KeyOutcome::Ignored,
+            KeyCode::Char('j') => KeyOutcome::Ignored,
+            KeyCode::Char('h') => KeyOutcome::Ignored,
+            KeyCode::Char('l') => KeyOutcome::Ignored,
+        
+            KeyCode::Char('w') => KeyOutcome::Ignored,
+            KeyCode::Char('s') => KeyOutcome::Ignored,
+            KeyCode::Char('a') => KeyOutcome::Ignored,
+            KeyCode::Char('d') => KeyOutcome::Ignored,
+
+            KeyCode::Char('y') => KeyOutcome::Ignored,
+            KeyCode::Char('u') => KeyOutcome::Ignored,
+            KeyCode::Char('b') => KeyOutcome::Ignored,
+            KeyCode::Char('n') => KeyOutcome::Ignored,
+
+            KeyCode::Char('x') if exports => { // the finished board, now with its mines
+                export_board(game);
+                KeyOutcome::Ignored
+            }
+            KeyCode::Char('r') if exports => {
+                export_replay(game);
+                KeyOutcome::Ignored
+            }
+            KeyCode::Char('x') | KeyCode::Char('r') => KeyOutcome::Ignored,
+            KeyCode::Char('v') => {
+                toggle_analysis(game);
+                KeyOutcome::Ignored
+            }
+
+            _ => KeyOutcome::Quit, // any other key should allow the user to quit
+        }
+    }
+}
+
+// a whole game over one connection, drawn with the same ui as a local one. the client's terminal size only
+// arrives through telnet, so the viewport is fixed and rebuilt whenever the client reports a new size
+fn run_remote(stream: TcpStream, preset: &Preset, compact: bool, status: &StatusBar) -> io::Result<()> {
+    let mut input = stream.try_clone()?;
+    input.set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut output = stream;
+    output.set_nodelay(true)?;
+    io::Write::write_all(&mut output, &telnet::NEGOTIATION)?;
+    execute!(output, EnterAlternateScreen, Hide)?;
+
+    let mut size = Rect::new(0, 0, 80, 24); // until the client says otherwise
+    let mut terminal = remote_terminal(&output, size)?;
+    let mut telnet = Telnet::default();
+
+    let topology = topology::build(preset.width, preset.height, false, false, Neighborhood::Moore);
+    let mut game = Game::new(topology, preset.difficulty.clone());
+    game.compact = compact;
+    game.message = Some("Welcome to termsweeper, press 'q' to leave".to_string());
+
+    let result = loop {
+        terminal.draw(|f| ui(f, &mut game, false, status, &[]))?;
+
+        let mut chunk = [0; 256];
+        let read = match io::Read::read(&mut input, &mut chunk) {
+            Ok(0) => break Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => continue,
+            Err(err) => break Err(err),
+        };
+
+        let events = match telnet.feed(&chunk[..read]) {
+            Ok(events) => events,
+            Err(err) => break Err(err),
+        };
+
+        let mut quit = false;
+        for event in events {
+            match event {
+                TelnetEvent::Resize(width, height) => {
+                    size = Rect::new(0, 0, width.max(1), height.max(1));
+                    terminal = remote_terminal(&output, size)?;
+                    terminal.clear()?;
                 }
+                TelnetEvent::Key(code) => {
+                    let fits = fits(&game, status, &[], size);
+                    quit |= matches!(handle_key(&mut game, code, fits, &mut [], false), KeyOutcome::Quit);
+                }
+            }
+        }
+        if quit { break Ok(()); }
+    };
+
+    let _ = execute!(output, LeaveAlternateScreen, Show);
+    result
+}
+
+fn remote_terminal(stream: &TcpStream, size: Rect) -> io::Result<Terminal<CrosstermBackend<TcpStream>>> {
+    Terminal::with_options(CrosstermBackend::new(stream.try_clone()?), TerminalOptions { viewport: Viewport::Fixed(size) })
+}
+
+// read only, the game on screen is replaced by every snapshot the player's terminal sends
+fn run_watch<B: tui::backend::Backend>(terminal: &mut Terminal<B>, mut watcher: Watcher, address: &str, compact: bool, hide_timer: bool, status: &StatusBar) -> io::Result<()> {
+    let mut game: Option<Game> = None;
+    let mut compact = compact;
+
+    loop {
+        if let Some(latest) = watcher.poll() {
+            game = Some(latest);
+        }
+        if let Some(game) = &mut game {
+            game.compact = compact;
+            game.message = Some(
+                if watcher.player_left { "The player has stopped publishing, press 'q' to quit".to_string() }
+                else { format!("Watching {}, press 'q' to stop", address) }
+            );
+        }
+
+        terminal.draw(|f| match &mut game {
+            Some(game) => ui(f, game, hide_timer, status, &[]),
+            None => f.render_widget(Paragraph::new(Text::raw("Waiting for the game...")), f.size()),
+        })?;
+
+        if event::poll(Duration::from_millis(10))? {
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('q') => { return Ok(()); }
+                    KeyCode::Esc => { return Ok(()); }
+                    KeyCode::Char('c') => { compact = !compact; }
+                    _ => {}
+                },
+                Event::Resize(_, _) => { terminal.autoresize()?; }
+                _ => {}
+            }
+        }
+    }
+}
+
+// a network game may want a say in the move first
+fn act(game: &mut Game, sessions: &mut [Box<dyn Session>], action: Action) {
+    if sessions.iter_mut().any(|session| session.act(game, action)) { return; }
+    match action {
+        Action::Reveal => game.reveal_cell(),
+        Action::Flag => game.toggle_flag(),
+    }
+}
+
+fn run_replay<B: tui::backend::Backend>(terminal: &mut Terminal<B>, game: Game, events: Vec<(Duration, ReplayEvent)>, hide_timer: bool, status: &StatusBar) -> io::Result<()> {
+    let start = Instant::now();
+    let mut next_event = 0;
+    let position = Rc::new(Cell::new(Duration::ZERO));
+    let mut game = game.with_clock(Box::new(ReplayClock::new(position.clone())));
+
+    loop {
+        let elapsed = Instant::now().duration_since(start);
+        while next_event < events.len() && events[next_event].0 <= elapsed && game.game_state == GameState::ACTIVE {
+            position.set(events[next_event].0);
+            replay::apply(&mut game, events[next_event].1);
+            next_event += 1;
+        }
+        position.set(elapsed);
+
+        terminal.draw(|f| ui(f, &mut game, hide_timer, status, &[]))?;
+
+        if event::poll(Duration::from_millis(10))? {
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('q') => { return Ok(()); }
+                    KeyCode::Esc => { return Ok(()); }
+                    KeyCode::Char('c') => { game.compact = !game.compact; }
+                    _ => {}
+                },
+                Event::Resize(_, _) => { terminal.autoresize()?; }
+                _ => {}
             }
         }
     }
 }
 
-fn ui<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game, hide_timer: bool) {
+// every reveal marked on the finished board by whether the numbers had proven it
+fn toggle_analysis(game: &mut Game) {
+    if game.analysis.take().is_some() {
+        game.message = None;
+        return;
+    }
+    game.analysis = analysis::analyze(game);
+    game.message = Some(match &game.analysis {
+        Some(analysis) => analysis.summary(),
+        None => "There are no moves to analyze".to_string(),
+    });
+}
+
+fn export_board(game: &mut Game) {
+    if game.endless.is_some() {
+        game.message = Some("An endless board is too big to save".to_string());
+        return;
+    }
+    let reveal_mines = game.game_state != GameState::ACTIVE; // never give away mines mid game
+    let contents = layout::export(game.board(), reveal_mines);
+    save_export(game, "txt", Ok(contents), "Board");
+}
+
+fn export_replay(game: &mut Game) {
+    let contents = replay::export(game);
+    save_export(game, "rawvf", contents, "Replay");
+}
+
+fn save_export(game: &mut Game, extension: &str, contents: io::Result<String>, what: &str) {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let path = format!("termsweeper-{}.{}", stamp, extension);
+
+    game.message = Some(match contents.and_then(|contents| fs::write(&path, contents)) {
+        Ok(()) => format!("{} saved to {}", what, path),
+        Err(err) => format!("Could not save {}: {}", what.to_lowercase(), err),
+    });
+}
+
+// the grid plus room for the status lines above and below it and the race panel beside it
+fn required_size(game: &Game, grid_size: (u16, u16), status: &StatusBar, sessions: &[Box<dyn Session>]) -> (u16, u16) {
+    let (grid_width, grid_height) = grid_size;
+    let status_height = if status.is_empty() { 0 } else { 1 };
+    let scores_height = if game.versus.is_some() || game.lives.is_some() || game.endless.is_some() { 2 } else { 0 }; // keeps the grid centered with the scores or lives line on top
+    let panel_width = if sessions.iter().any(|session| !session.panel().is_empty()) { 2 * (net::PANEL_WIDTH + 2) } else { 0 }; // on both sides, so the grid stays centered
+    ((grid_width + 2 + panel_width).max(MIN_TERMINAL_WIDTH), grid_height + 4 + status_height + scores_height)
+}
+
+fn fits(game: &Game, status: &StatusBar, sessions: &[Box<dyn Session>], size: Rect) -> bool {
+    let (width, height) = required_size(game, game.grid_size(), status, sessions);
+    size.width >= width && size.height >= height
+}
+
+// how many rows a line takes once it is word wrapped to the width
+fn wrapped_height(line: &str, width: u16) -> u16 {
+    let mut rows = 1;
+    let mut used = 0;
+    for word in line.split_whitespace().map(|word| word.chars().count() as u16) {
+        if used > 0 && used + 1 + word > width {
+            rows += 1;
+            used = 0;
+        }
+        used += if used > 0 { word + 1 } else { word };
+    }
+    rows
+}
+
+fn ui<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game, hide_timer: bool, status: &StatusBar, sessions: &[Box<dyn Session>]) {
     let size = frame.size();
+
+    if game.endless.is_some() { // the board takes whatever room the lines around it leave
+        let (cell_width, cell_height) = game.cell_size();
+        let (_, spare_height) = required_size(game, (0, 0), status, sessions);
+        game.fit_window((size.width.saturating_sub(2) / cell_width).max(1) as usize, (size.height.saturating_sub(spare_height) / cell_height).max(1) as usize);
+    }
+
+    if !fits(game, status, sessions, size) {
+        let (width, height) = required_size(game, game.grid_size(), status, sessions);
+        let mut text = format!("Terminal too small, need {}x{} (now {}x{})", width, height, size.width, size.height);
+
+        let (compact_width, compact_height) = required_size(game, game.board().topology().grid_size(game::COMPACT_CELL_WIDTH, game::COMPACT_CELL_HEIGHT), status, sessions);
+        if !game.compact && size.width >= compact_width && size.height >= compact_height { text.push_str("\nPress 'c' for compact mode"); }
+        text.push_str("\nPress 'q' to quit");
+
+        let too_small_display = Paragraph::new(Text::raw(text))
+            .style(Style::default().fg(Color::White))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(too_small_display, size);
+        return;
+    }
     
     let top_left_text = 
-        if game.game_state == GameState::ACTIVE { Paragraph::new(Text::raw(format!("{} Flags Left", game.flags_available))).style(Style::default().fg(Color::White)) }
+        if let Some(endless) = &game.endless { Paragraph::new(Text::raw(format!("Score {}", endless.score()))).style(Style::default().fg(Color::White)) }
+        else if game.game_state == GameState::ACTIVE { Paragraph::new(Text::raw(format!("{} Flags Left", game.flags_available))).style(Style::default().fg(Color::White)) }
         else { Paragraph::new(Text::raw("Game Over!".to_string())).style(Style::default().fg(Color::White)) };
 
     frame.render_widget(top_left_text, Rect::new(2, 1, 20, 1));
+
+    if let Some(versus) = &game.versus { // the scores go on their own line, the one with the turn is marked
+        let mut spans = Vec::new();
+        for (player, score) in versus.scores.iter().enumerate() {
+            let marker = if player == versus.turn && game.game_state == GameState::ACTIVE { ">" } else { " " };
+            spans.push(Span::styled(format!("{}Player {}: {}  ", marker, player + 1, score), Style::default().fg(game::PLAYER_COLORS[player])));
+        }
+        let outcome = match game.game_state {
+            GameState::WINNER(player) => format!("Player {} wins!", player + 1),
+            GameState::DRAW => "It's a draw!".to_string(),
+            _ => String::new(),
+        };
+        spans.push(Span::styled(outcome, Style::default().fg(Color::White)));
+        frame.render_widget(Paragraph::new(Line::from(spans)), Rect::new(2, 2, size.width.saturating_sub(4), 1));
+    }
+
+    if let Some(lives) = &game.lives { // a heart for each life left, an empty one for each lost
+        let text =
+            if lives.total <= 10 { format!("Lives: {}", ("♥ ".repeat(lives.left as usize) + &"♡ ".repeat((lives.total - lives.left) as usize)).trim_end()) }
+            else { format!("Lives: {} of {}", lives.left, lives.total) };
+        frame.render_widget(Paragraph::new(Text::raw(text)).style(Style::default().fg(Color::LightRed)), Rect::new(2, 2, size.width.saturating_sub(4), 1));
+    }
+    
+    if let Some(endless) = &game.endless {
+        let text = format!("Revealed {} | Mines hit {} | Seed {}", endless.revealed, endless.mines_hit, game.seed);
+        frame.render_widget(Paragraph::new(Text::raw(text)).style(Style::default().fg(Color::White)), Rect::new(2, 2, size.width.saturating_sub(4), 1));
+    }
     
     if !hide_timer {
-        let elapsed = 
-            if game.game_state == GameState::ACTIVE { Instant::now().duration_since(game.game_time) }
-            else { game.game_end_time.duration_since(game.game_start_time) };
-        
+        let elapsed = game.elapsed();
         let elapsed_seconds = elapsed.as_secs();
         let timer_text = format!(
             "Time: {:02}:{:02}:{:03}", 
@@ -188,7 +872,7 @@ fn ui<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game, hide_timer: bool)
         let timer_display = Paragraph::new(Text::raw(timer_text.to_owned()))
             .style(Style::default().fg(Color::White));
         
-        frame.render_widget(timer_display, Rect::new((size.width - timer_text.len() as u16) / 2, 1, timer_text.len() as u16, 1));
+        frame.render_widget(timer_display, Rect::new(size.width.saturating_sub(timer_text.len() as u16) / 2, 1, timer_text.len() as u16, 1));
     }
     
     let right_text_width = 18;
@@ -199,5 +883,40 @@ fn ui<B: Backend>(frame: &mut tui::Frame<B>, game: &mut Game, hide_timer: bool)
         .style(Style::default().fg(Color::White));
     frame.render_widget(top_right_text, Rect::new(right_text_x, 1, right_text_width, 1));
     
+    let analysis_hint = Some("Press 'v' to see which moves were guesses".to_string()).filter(|_| game.game_state != GameState::ACTIVE && game.versus.is_none());
+    if let Some(message) = game.message.as_ref().or(analysis_hint.as_ref()) {
+        let message_display = Paragraph::new(Text::raw(message.to_owned()))
+            .style(Style::default().fg(Color::White));
+        frame.render_widget(message_display, Rect::new(2, size.height.saturating_sub(2), size.width.saturating_sub(4), 1));
+    }
+    
+    if !status.is_empty() { // right under the grid, centered like it
+        let (_, grid_height) = game.grid_size();
+        let status_text = status.text(game);
+        let status_width = (status_text.chars().count() as u16).min(size.width);
+        let status_y = size.height.saturating_sub(grid_height) / 2 + grid_height;
+        let status_display = Paragraph::new(Text::raw(status_text))
+            .style(Style::default().fg(Color::White));
+        frame.render_widget(status_display, Rect::new((size.width - status_width) / 2, status_y, status_width, 1));
+    }
+
+    let panel = sessions.iter().flat_map(|session| session.panel()).collect::<Vec<_>>();
+    if !panel.is_empty() { // along the right edge, level with the top of the grid unless the lines need more room
+        let (_, grid_height) = game.grid_size();
+        let wanted_height = panel.iter().map(|(line, _)| wrapped_height(line, net::PANEL_WIDTH)).sum::<u16>();
+        let panel_y = (size.height.saturating_sub(grid_height) / 2).min(size.height.saturating_sub(wanted_height + 2)).max(3);
+        let panel_height = size.height.saturating_sub(panel_y + 2);
+        let panel: Vec<Line> = panel.into_iter().map(|(line, color)| Line::from(Span::styled(line, Style::default().fg(color)))).collect();
+        frame.render_widget(Paragraph::new(panel).wrap(Wrap { trim: true }), Rect::new(size.width.saturating_sub(net::PANEL_WIDTH + 1), panel_y, net::PANEL_WIDTH, panel_height));
+    }
+
+    if game.paused() { // no peeking at the board while the clock is stopped
+        let paused_text = "Paused, press 'p' to resume";
+        let paused_display = Paragraph::new(Text::raw(paused_text))
+            .style(Style::default().fg(Color::White));
+        frame.render_widget(paused_display, Rect::new(size.width.saturating_sub(paused_text.len() as u16) / 2, size.height / 2, (paused_text.len() as u16).min(size.width), 1));
+        return;
+    }
+
     game::render_grid(frame, game);
 }
\ No newline at end of file
@@ -0,0 +1,237 @@
+use std::{io::{self, Read, Write}, net::{TcpListener, TcpStream, ToSocketAddrs}, time::{Duration, Instant}};
+
+use tui::style::Color;
+
+use crate::cli::MAX_BOARD_SIDE;
+use crate::game::{Action, Game};
+use crate::share::Challenge;
+
+pub const PANEL_WIDTH: u16 = 20;
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKLOG: usize = 1 << 20; // bytes a peer may fall behind before it is given up on
+const MAX_LINE: usize = 4096; // far longer than any message, a peer that sends more without a newline is up to no good
+
+// a game played with others over plain TCP. the host listens and greets every joiner with
+//   WELCOME <player> <challenge code> <race|coop>
+// after which each mode sends its own one line messages of space separated fields
+pub trait Session {
+    // takes in new players and every message waiting, called once a frame and never blocks
+    fn poll(&mut self, game: &mut Game);
+
+    // a reveal or flag at the cursor, returns false to let the game handle it as usual
+    fn act(&mut self, _game: &mut Game, _action: Action) -> bool { false }
+
+    // what the side panel shows, one colored line each
+    fn panel(&self) -> Vec<(String, Color)>;
+}
+
+pub struct Welcome {
+    pub player: usize,
+    pub setup: Challenge,
+    pub coop: bool,
+}
+
+// "0.0.0.0" lets the rest of the LAN in, localhost included, "127.0.0.1" keeps it to this machine
+pub fn listen(interface: &str, port: u16) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind((interface, port))?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+// a connection that waits a while for each read, until it is switched to not waiting at all
+pub fn open(address: &str) -> io::Result<Peer> {
+    let target = address.to_socket_addrs()?.next().ok_or_else(|| invalid("the address did not resolve"))?;
+    let stream = TcpStream::connect_timeout(&target, JOIN_TIMEOUT)?;
+    stream.set_read_timeout(Some(JOIN_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    Ok(Peer::new(stream, 0))
+}
+
+// connects and waits for the welcome, which carries the board to play
+pub fn connect(address: &str) -> io::Result<(Peer, Welcome)> {
+    let mut host = open(address)?;
+
+    let deadline = Instant::now() + JOIN_TIMEOUT;
+    let mut lines = loop {
+        let lines = host.receive().ok_or_else(|| invalid("the host closed the connection"))?;
+        if !lines.is_empty() { break lines; }
+        if Instant::now() > deadline { return Err(io::Error::new(io::ErrorKind::TimedOut, "the host never sent the board")); }
+    };
+    let welcome = lines.remove(0);
+    host.unread(lines); // the game so far, left for the session to pick up
+
+    let fields: Vec<&str> = welcome.split_whitespace().collect();
+    let ["WELCOME", player, code, mode] = fields[..] else { return Err(invalid("that is not a termsweeper game")) };
+    let player = player.parse().map_err(|_| invalid("the host sent a bad player number"))?;
+    let setup = Challenge::decode(code)?;
+    if setup.width > MAX_BOARD_SIDE || setup.height > MAX_BOARD_SIDE { return Err(invalid("the board is too big")); }
+
+    host.set_nonblocking()?;
+    Ok((host, Welcome { player, setup, coop: mode == "coop" }))
+}
+
+// every connection the host has not taken in yet, ready to be greeted
+pub fn accept(listener: &TcpListener, next_player: &mut usize) -> Vec<Peer> {
+    let mut joined = Vec::new();
+    while let Ok((stream, _)) = listener.accept() {
+        if stream.set_nonblocking(true).is_err() { continue; }
+        let _ = stream.set_nodelay(true);
+        joined.push(Peer::new(stream, *next_player));
+        *next_player += 1;
+    }
+    joined
+}
+
+// one open connection, with whatever part of a line has arrived so far
+pub struct Peer {
+    stream: TcpStream,
+    pub player: usize,
+    buffer: Vec<u8>,
+    outgoing: Vec<u8>, // what deliver could not write yet
+}
+
+impl Peer {
+    fn new(stream: TcpStream, player: usize) -> Self { Peer { stream, player, buffer: Vec::new(), outgoing: Vec::new() } }
+
+    // every whole line that arrived since the last call, None once the other side has hung up or stopped
+    // taking in what is sent to it
+    pub fn receive(&mut self) -> Option<Vec<String>> {
+        if self.flush().is_err() { return None; }
+
+        let mut chunk = [0; 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    self.buffer.extend_from_slice(&chunk[..read]);
+                    if read < chunk.len() || self.buffer.len() >= MAX_BACKLOG { break; } // anything more waits for the next call
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        if self.buffer.len() > MAX_LINE { return None; }
+        Some(lines)
+    }
+
+    pub fn set_nonblocking(&self) -> io::Result<()> {
+        self.stream.set_read_timeout(None)?;
+        self.stream.set_nonblocking(true)
+    }
+
+    // puts lines back so the next receive hands them out again
+    fn unread(&mut self, lines: Vec<String>) {
+        let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        self.buffer.splice(0..0, text.into_bytes());
+    }
+
+    // whatever the other side does not take in right away waits for the next flush, so a slow peer never holds
+    // up the game and a message always arrives whole
+    pub fn deliver(&mut self, text: &str) -> io::Result<()> {
+        self.outgoing.extend_from_slice(text.as_bytes());
+        self.flush()
+    }
+
+    // writes as much of what deliver left over as goes out without waiting. fails once the peer has
+    // fallen too far behind
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => { self.outgoing.drain(..written); }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        if self.outgoing.len() > MAX_BACKLOG { return Err(invalid("the peer fell too far behind")); }
+        Ok(())
+    }
+}
+
+// the connections of a race or co-op game, whatever the mode. the host listens, greets joiners and passes
+// messages between them, a joiner only ever talks to the host
+pub struct Link {
+    pub player: usize, // the host is always player 0
+    listener: Option<TcpListener>, // only the host listens
+    code: String, // the board every joiner is handed
+    mode: &'static str, // the last word of the welcome, race or coop
+    peers: Vec<Peer>, // every joiner on the host, just the host on a joiner
+    next_player: usize,
+    pub host_left: bool,
+}
+
+impl Link {
+    pub fn host(listener: TcpListener, code: String, mode: &'static str) -> Self {
+        Link { player: 0, listener: Some(listener), code, mode, peers: Vec::new(), next_player: 1, host_left: false }
+    }
+
+    pub fn joined(host: Peer, player: usize) -> Self {
+        Link { player, listener: None, code: String::new(), mode: "", peers: vec![host], next_player: 0, host_left: false }
+    }
+
+    pub fn is_host(&self) -> bool { self.listener.is_some() }
+
+    // new joiners, welcomed already. the mode catches them up on the game so far and then hands them to add
+    pub fn accept(&mut self) -> Vec<Peer> {
+        let Some(listener) = &self.listener else { return Vec::new() };
+        let mut joined = accept(listener, &mut self.next_player);
+        joined.retain_mut(|peer| peer.deliver(&format!("WELCOME {} {} {}\n", peer.player, self.code, self.mode)).is_ok());
+        joined
+    }
+
+    // a joiner that cannot take in the catch up is dropped on the next receive like any other
+    pub fn add(&mut self, mut peer: Peer, catch_up: &str) {
+        let _ = peer.deliver(catch_up);
+        self.peers.push(peer);
+    }
+
+    // every message waiting, tagged with who sent it, and the joiners that hung up. the host tells everyone
+    // else about those, on a joiner a hang up can only be the host leaving
+    pub fn receive(&mut self) -> (Vec<(usize, String)>, Vec<usize>) {
+        let (received, gone) = receive_all(&mut self.peers);
+        let mut left = Vec::new();
+        for index in gone.into_iter().rev() {
+            let peer = self.peers.remove(index);
+            if self.is_host() { left.push(peer.player); }
+            else { self.host_left = true; }
+        }
+        for player in &left { self.broadcast(&format!("LEFT {}", player), None); }
+        (received, left)
+    }
+
+    pub fn broadcast(&mut self, line: &str, except: Option<usize>) { broadcast(&mut self.peers, line, except); }
+
+    // the host passes a message on to everyone but the one who sent it
+    pub fn relay(&mut self, line: &str, from: usize) {
+        if self.is_host() { self.broadcast(line, Some(from)); }
+    }
+}
+
+// a peer that cannot keep up shows up as hung up on the next receive
+pub fn broadcast(peers: &mut [Peer], line: &str, except: Option<usize>) {
+    let text = format!("{}\n", line);
+    for peer in peers.iter_mut().filter(|peer| Some(peer.player) != except) { let _ = peer.deliver(&text); }
+}
+
+// every message from every peer, tagged with who sent it, and the positions of the peers that hung up
+pub fn receive_all(peers: &mut [Peer]) -> (Vec<(usize, String)>, Vec<usize>) {
+    let mut received = Vec::new();
+    let mut gone = Vec::new();
+    for (index, peer) in peers.iter_mut().enumerate() {
+        match peer.receive() {
+            Some(lines) => received.extend(lines.into_iter().map(|line| (peer.player, line))),
+            None => gone.push(index),
+        }
+    }
+    (received, gone)
+}
+
+fn invalid(message: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, message) }
@@ -0,0 +1,21 @@
+use crate::game::Difficulty;
+
+// named board setups, sized so the mine counts land on (or right next to) the classic ones
+pub struct Preset {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub difficulty: Difficulty,
+}
+
+pub const PRESETS: [Preset; 3] = [
+    Preset { name: "beginner", width: 9, height: 9, difficulty: Difficulty::EASY },
+    Preset { name: "intermediate", width: 16, height: 16, difficulty: Difficulty::MEDIUM },
+    Preset { name: "expert", width: 30, height: 16, difficulty: Difficulty::HARD },
+];
+
+pub const DAILY_PRESET: &str = "intermediate"; // never change this, it would change every past daily board
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name.to_lowercase())
+}
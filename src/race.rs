@@ -0,0 +1,166 @@
+use std::{net::TcpListener, time::Duration};
+
+use tui::style::Color;
+
+use crate::game::{Game, GameState};
+use crate::net::{Link, Peer, Session};
+
+// everyone plays their own copy of the host's board and sends a line whenever their progress changes,
+// which the host passes on to the rest:
+//   PROGRESS <player> <cleared> <total> <state> <millis>    anyone, relayed by the host
+//   LEFT <player>                                           host, when a joiner hangs up
+#[derive(Clone, Copy, PartialEq)]
+pub enum RaceState {
+    Playing,
+    Won,
+    Lost,
+}
+
+// how far one player has got, the time is only final once they won or lost
+#[derive(Clone, Copy)]
+pub struct Progress {
+    pub player: usize,
+    pub cleared: usize,
+    pub total: usize,
+    pub state: RaceState,
+    pub time: Duration,
+    pub left: bool,
+}
+
+impl Progress {
+    fn of(player: usize, game: &Game) -> Self {
+        let total = game.board().width() * game.board().height() - game.board().mine_cells();
+        let state = match game.game_state {
+            GameState::SUCSESS => RaceState::Won,
+            GameState::ACTIVE => RaceState::Playing,
+            _ => RaceState::Lost,
+        };
+        Progress { player, cleared: total - game.hidden_cells_remaining, total, state, time: game.elapsed(), left: false }
+    }
+
+    fn encode(&self) -> String {
+        let state = match self.state {
+            RaceState::Playing => "playing",
+            RaceState::Won => "won",
+            RaceState::Lost => "lost",
+        };
+        format!("PROGRESS {} {} {} {} {}", self.player, self.cleared, self.total, state, self.time.as_millis())
+    }
+
+    fn decode(fields: &[&str]) -> Option<Self> {
+        let [player, cleared, total, state, millis] = fields[..] else { return None };
+        let state = match state {
+            "playing" => RaceState::Playing,
+            "won" => RaceState::Won,
+            "lost" => RaceState::Lost,
+            _ => return None,
+        };
+        Some(Progress {
+            player: player.parse().ok()?,
+            cleared: cleared.parse().ok()?,
+            total: total.parse().ok()?,
+            state,
+            time: Duration::from_millis(millis.parse().ok()?),
+            left: false,
+        })
+    }
+
+    fn describe(&self) -> String {
+        let name = format!("Player {}", self.player + 1);
+        match self.state {
+            _ if self.left && self.state == RaceState::Playing => format!("{:<10}left", name),
+            RaceState::Playing => format!("{:<10}{}/{}", name, self.cleared, self.total),
+            RaceState::Won => format!("{:<10}won {:.1}s", name, self.time.as_secs_f64()),
+            RaceState::Lost => format!("{:<10}lost {}/{}", name, self.cleared, self.total),
+        }
+    }
+}
+
+pub struct Race {
+    link: Link,
+    opponents: Vec<Progress>, // sorted by player
+    sent: Option<Progress>, // the last progress of our own that went out
+}
+
+impl Race {
+    pub fn host(listener: TcpListener, code: String) -> Self {
+        Race { link: Link::host(listener, code, "race"), opponents: Vec::new(), sent: None }
+    }
+
+    pub fn joined(host: Peer, player: usize) -> Self {
+        Race { link: Link::joined(host, player), opponents: Vec::new(), sent: None }
+    }
+
+    // sends our own progress whenever a cell is cleared or the game ends
+    fn update(&mut self, game: &Game) {
+        let progress = Progress::of(self.link.player, game);
+        let changed = self.sent.is_none_or(|sent| sent.cleared != progress.cleared || sent.state != progress.state);
+        if !changed { return; }
+
+        self.link.broadcast(&progress.encode(), None);
+        self.sent = Some(progress);
+    }
+
+    fn welcome(&mut self, peer: Peer) {
+        let mut catch_up = String::new();
+        for progress in self.sent.iter().chain(self.opponents.iter()) { // everyone so far
+            catch_up.push_str(&format!("{}\n", progress.encode()));
+            if progress.left { catch_up.push_str(&format!("LEFT {}\n", progress.player)); }
+        }
+        self.link.add(peer, &catch_up);
+    }
+
+    // returns whether the line was a message worth passing on
+    fn handle(&mut self, line: &str) -> bool {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.first() {
+            Some(&"PROGRESS") => {
+                let Some(progress) = Progress::decode(&fields[1..]) else { return false };
+                if progress.player == self.link.player { return false; }
+                match self.opponents.iter_mut().find(|known| known.player == progress.player) {
+                    Some(known) => *known = Progress { left: known.left, ..progress },
+                    None => {
+                        self.opponents.push(progress);
+                        self.opponents.sort_by_key(|known| known.player);
+                    }
+                }
+                true
+            }
+            Some(&"LEFT") => {
+                let Some(player) = fields.get(1).and_then(|player| player.parse().ok()) else { return false };
+                self.mark_left(player);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn mark_left(&mut self, player: usize) {
+        if let Some(known) = self.opponents.iter_mut().find(|known| known.player == player) { known.left = true; }
+    }
+}
+
+impl Session for Race {
+    fn poll(&mut self, game: &mut Game) {
+        for peer in self.link.accept() { self.welcome(peer); }
+
+        let (received, left) = self.link.receive();
+        for (from, line) in received {
+            if self.handle(&line) { self.link.relay(&line, from); }
+        }
+        for player in left { self.mark_left(player); }
+
+        self.update(game); // the others keep racing while this game is paused or over
+    }
+
+    // one line per opponent
+    fn panel(&self) -> Vec<(String, Color)> {
+        let mut lines = vec![format!("Race, you are P{}", self.link.player + 1)];
+        if self.opponents.is_empty() {
+            lines.push(if self.link.is_host() { "Waiting for players".to_string() } else { "No one else yet".to_string() });
+        }
+        lines.extend(self.opponents.iter().map(|progress| progress.describe()));
+        if self.link.host_left { lines.push("The host has left".to_string()); }
+        lines.into_iter().map(|line| (line, Color::White)).collect()
+    }
+}
@@ -0,0 +1,245 @@
+use std::{cell::Cell, env, io, rc::Rc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use crate::cli::MAX_BOARD_SIDE;
+use crate::clock::Clock;
+use crate::game::{CellState, Game, GameState, MoveKind};
+use crate::layout::Layout;
+
+// RAW Minesweeper (.rawvf) replays, the plain text format most community replay tools read and write.
+// a header of "Key: value" lines, then a "Board:" section of '*' and '0' rows, then an "Events:"
+// section of timed mouse events such as "1.23 lc 4 7 (56 104)" with 1 based columns and rows
+const PIXELS_PER_CELL: usize = 16; // events also carry the pixel position of the click, assume classic 16px squares
+
+#[derive(Clone, Copy)]
+pub enum ReplayEvent {
+    Reveal(usize, usize),
+    Chord(usize, usize),
+    Flag(usize, usize),
+    Cursor(usize, usize),
+}
+
+pub struct Replay {
+    pub player: String,
+    pub layout: Layout,
+    pub events: Vec<(Duration, ReplayEvent)>,
+}
+
+pub fn export(game: &Game) -> io::Result<String> {
+    let board = game.board();
+    if game.game_state == GameState::ACTIVE || game.moves.is_empty() {
+        return Err(io::Error::other("replays can only be saved once the game is over"));
+    }
+    if board.max_mines() > 1 || !board.topology().is_classic() || game.versus.is_some() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "RAW replays only describe classic square boards"));
+    }
+
+    let (width, height) = (board.width(), board.height());
+    let mines = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).filter(|&(x, y)| board.is_mine(x, y)).count();
+    let level = match (width, height, mines) {
+        (9, 9, 10) => "Beginner",
+        (16, 16, 40) => "Intermediate",
+        (30, 16, 99) => "Expert",
+        _ => "Custom",
+    };
+
+    let start = game.moves[0].time; // RAW times count from the first click
+    let end = game.elapsed().saturating_sub(start);
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    let player = env::var("USER").unwrap_or_else(|_| "Anonymous".to_string());
+
+    let mut text = String::new();
+    text.push_str("RawVF_Version: Rev5\n");
+    text.push_str("Program: Termsweeper\n");
+    text.push_str(&format!("Version: {}\n", env!("CARGO_PKG_VERSION")));
+    text.push_str(&format!("Player: {}\n", player));
+    text.push_str(&format!("Timestamp: {}\n", stamp));
+    text.push_str(&format!("Level: {}\n", level));
+    text.push_str(&format!("Width: {}\n", width));
+    text.push_str(&format!("Height: {}\n", height));
+    text.push_str(&format!("Mines: {}\n", mines));
+    text.push_str("Marks: Off\n");
+    text.push_str(&format!("Time: {:.2}\n", end.as_secs_f64()));
+    text.push_str(&format!("BBBV: {}\n", board.three_bv()));
+
+    text.push_str("Board:\n");
+    for y in 0..height {
+        for x in 0..width {
+            text.push(if board.is_mine(x, y) { '*' } else { '0' });
+        }
+        text.push('\n');
+    }
+
+    text.push_str("Events:\n");
+    text.push_str("0.00 start\n");
+    for step in &game.moves {
+        let time = step.time.saturating_sub(start).as_secs_f64();
+        let (down, up) = match step.kind {
+            MoveKind::Reveal => ("lc", "lr"),
+            MoveKind::Chord => ("mc", "mr"),
+            MoveKind::Flag => ("rc", "rr"),
+        };
+        let position = format!("{} {} ({} {})", step.x + 1, step.y + 1, step.x * PIXELS_PER_CELL + PIXELS_PER_CELL / 2, step.y * PIXELS_PER_CELL + PIXELS_PER_CELL / 2);
+        text.push_str(&format!("{:.2} {} {}\n", time, down, position));
+        text.push_str(&format!("{:.2} {} {}\n", time, up, position));
+    }
+    let outcome = if game.game_state == GameState::SUCSESS { "won" } else { "blast" };
+    text.push_str(&format!("{:.2} {}\n", end.as_secs_f64(), outcome));
+
+    Ok(text)
+}
+
+pub fn parse(text: &str) -> io::Result<Replay> {
+    let mut lines = text.lines().map(str::trim);
+    let mut width = 0;
+    let mut height = 0;
+    let mut player = "Anonymous".to_string();
+
+    for line in lines.by_ref() {
+        if line == "Board:" { break; }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key {
+                "Width" => width = value.parse().map_err(|_| invalid("the Width header is not a number"))?,
+                "Height" => height = value.parse().map_err(|_| invalid("the Height header is not a number"))?,
+                "Player" => player = value.to_string(),
+                _ => {}
+            }
+        }
+    }
+    if width == 0 || height == 0 { return Err(invalid("the replay has no Width and Height headers")); }
+    if width > MAX_BOARD_SIDE || height > MAX_BOARD_SIDE { return Err(invalid(&format!("the board is bigger than {}x{}", MAX_BOARD_SIDE, MAX_BOARD_SIDE))); }
+
+    let mut mines = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = lines.next().ok_or_else(|| invalid("the Board section is missing rows"))?;
+        if row.chars().count() != width { return Err(invalid(&format!("board row {} is not {} cells wide", y + 1, width))); }
+        mines.extend(row.chars().map(|c| (c == '*') as u8));
+    }
+
+    if !lines.by_ref().any(|line| line == "Events:") { return Err(invalid("the replay has no Events section")); }
+
+    let mut events = Vec::new();
+    let mut left_down = false;
+    let mut right_down = false;
+
+    for line in lines {
+        let mut fields = line.split_whitespace();
+        let (Some(time), Some(kind)) = (fields.next(), fields.next()) else { continue };
+        let Ok(time) = time.parse::<f64>() else { continue };
+        let Ok(time) = Duration::try_from_secs_f64(time.max(0.0)) else { continue }; // inf or too far out to be a real time
+
+        let cell = match (fields.next().and_then(|x| x.parse::<usize>().ok()), fields.next().and_then(|y| y.parse::<usize>().ok())) {
+            (Some(x), Some(y)) if (1..=width).contains(&x) && (1..=height).contains(&y) => Some((x - 1, y - 1)),
+            _ => None,
+        };
+        let Some((x, y)) = cell else { continue }; // start, won, blast and friends carry no position
+
+        // RAW records raw mouse buttons, turn them back into moves
+        let event = match kind {
+            "lc" => { left_down = true; None }
+            "rc" => {
+                right_down = true;
+                if left_down { None } else { Some(ReplayEvent::Flag(x, y)) }
+            }
+            "lr" => {
+                left_down = false;
+                if right_down { Some(ReplayEvent::Chord(x, y)) } else { Some(ReplayEvent::Reveal(x, y)) } // both buttons at once chord
+            }
+            "rr" => { right_down = false; None }
+            "mr" => Some(ReplayEvent::Chord(x, y)),
+            "mv" => Some(ReplayEvent::Cursor(x, y)),
+            _ => None,
+        };
+
+        if let Some(event) = event { events.push((time, event)); }
+    }
+
+    Ok(Replay { player, layout: Layout { width, height, mines, states: None }, events })
+}
+
+// the game clock during playback, it reads the shared replay position so every move is timed as recorded
+// no matter how late the terminal gets around to applying it
+pub struct ReplayClock {
+    origin: Instant,
+    position: Rc<Cell<Duration>>,
+}
+
+impl ReplayClock {
+    pub fn new(position: Rc<Cell<Duration>>) -> Self {
+        ReplayClock { origin: Instant::now(), position }
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> Instant { self.origin + self.position.get() }
+}
+
+// plays one event against the game, the caller decides when
+pub fn apply(game: &mut Game, event: ReplayEvent) {
+    match event {
+        ReplayEvent::Reveal(x, y) => {
+            game.set_cursor(x, y);
+            game.reveal_cell();
+        }
+        ReplayEvent::Chord(x, y) => {
+            game.set_cursor(x, y);
+            if game.board().cell(x, y).cell_state == CellState::REVEALED { game.reveal_cell(); }
+        }
+        ReplayEvent::Flag(x, y) => {
+            game.set_cursor(x, y);
+            game.toggle_flag();
+        }
+        ReplayEvent::Cursor(x, y) => { game.set_cursor(x, y); }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use crate::topology::{self, Neighborhood};
+
+    const HEADER: &str = "RawVF_Version: Rev5\nWidth: 3\nHeight: 2\nMines: 1\nBoard:\n*00\n000\nEvents:\n";
+
+    #[test]
+    fn an_exported_game_parses_back_to_its_board_and_moves() {
+        let layout = Layout::parse("*..\n...\n...\n").unwrap();
+        let mut game = Game::from_layout(topology::build(3, 3, false, false, Neighborhood::Moore), &layout).unwrap();
+        game.apply_at(0, 0, Action::Flag);
+        game.apply_at(2, 2, Action::Reveal);
+        assert!(game.game_state == GameState::SUCSESS);
+
+        let replay = parse(&export(&game).unwrap()).unwrap();
+        assert_eq!((replay.layout.width, replay.layout.height), (3, 3));
+        assert_eq!(replay.layout.mines, layout.mines);
+        assert_eq!(replay.events.len(), 2);
+        assert!(matches!(replay.events[0].1, ReplayEvent::Flag(0, 0)));
+        assert!(matches!(replay.events[1].1, ReplayEvent::Reveal(2, 2)));
+    }
+
+    #[test]
+    fn both_buttons_together_are_a_chord() {
+        let replay = parse(&format!("{}0.50 lc 2 2 (24 24)\n0.60 rc 2 2 (24 24)\n0.70 lr 2 2 (24 24)\n0.80 rr 2 2 (24 24)\n", HEADER)).unwrap();
+        assert_eq!(replay.events.len(), 1);
+        assert!(matches!(replay.events[0], (time, ReplayEvent::Chord(1, 1)) if time == Duration::from_millis(700)));
+    }
+
+    #[test]
+    fn times_that_do_not_fit_a_duration_are_skipped() {
+        let replay = parse(&format!("{}inf lr 1 1 (8 8)\n1e300 lr 1 1 (8 8)\n-1 lr 3 2 (40 24)\n", HEADER)).unwrap();
+        assert_eq!(replay.events.len(), 1);
+        assert!(matches!(replay.events[0], (Duration::ZERO, ReplayEvent::Reveal(2, 1))));
+    }
+
+    #[test]
+    fn oversized_or_broken_replays_are_turned_away() {
+        assert!(parse("Width: 5000\nHeight: 5000\nBoard:\n").is_err());
+        assert!(parse("Board:\n*\nEvents:\n").is_err());
+        assert!(parse("Width: 3\nHeight: 2\nBoard:\n*00\n00\nEvents:\n").is_err());
+        assert!(parse("Width: 3\nHeight: 2\nBoard:\n*00\n000\n").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+
+use crate::game::{CellState, Difficulty, Game, GameState};
+use crate::stats;
+use crate::topology::Neighborhood;
+
+// boards wider or taller than this get no thumbnail, it would not fit in a chat message
+const THUMBNAIL_MAX_WIDTH: usize = 40;
+const THUMBNAIL_MAX_HEIGHT: usize = 30;
+
+// everything needed to lay out the exact same mines again, written as a short shell safe code like
+// "16x16m-8-8-3f9a0c2e71b4d586" or "20x12h+hex+wrap+m3-10-6-3f9a0c2e71b4d586": the size and difficulty,
+// any non classic rules, the first click and the seed in hex
+#[derive(Clone)]
+pub struct Challenge {
+    pub width: usize,
+    pub height: usize,
+    pub difficulty: Difficulty,
+    pub hex: bool,
+    pub wrap: bool,
+    pub neighborhood: Neighborhood,
+    pub max_mines: u8,
+    pub start: (usize, usize),
+    pub seed: u64,
+}
+
+impl Challenge {
+    pub fn encode(&self) -> String {
+        let difficulty = match self.difficulty {
+            Difficulty::EASY => 'e',
+            Difficulty::MEDIUM => 'm',
+            Difficulty::HARD => 'h',
+        };
+
+        let mut code = format!("{}x{}{}", self.width, self.height, difficulty);
+        if self.hex { code.push_str("+hex"); }
+        if self.wrap { code.push_str("+wrap"); }
+        if self.neighborhood != Neighborhood::Moore { code.push_str(&format!("+{}", self.neighborhood.name())); }
+        if self.max_mines > 1 { code.push_str(&format!("+m{}", self.max_mines)); }
+
+        format!("{}-{}-{}-{:x}", code, self.start.0, self.start.1, self.seed)
+    }
+
+    pub fn decode(code: &str) -> io::Result<Self> {
+        let fields: Vec<&str> = code.trim().split('-').collect();
+        let [board, x, y, seed] = fields[..] else { return Err(invalid("expected four fields separated by '-'")) };
+
+        let mut rules = board.split('+');
+        let size = rules.next().unwrap_or_default();
+        let (width, rest) = size.split_once('x').ok_or_else(|| invalid("the board size is missing its 'x'"))?;
+        let difficulty = match rest.chars().last() {
+            Some('e') => Difficulty::EASY,
+            Some('m') => Difficulty::MEDIUM,
+            Some('h') => Difficulty::HARD,
+            _ => return Err(invalid("the board size does not end in a difficulty letter")),
+        };
+
+        let mut challenge = Challenge {
+            width: number(width, "width")?,
+            height: number(&rest[..rest.len() - 1], "height")?,
+            difficulty,
+            hex: false,
+            wrap: false,
+            neighborhood: Neighborhood::Moore,
+            max_mines: 1,
+            start: (number(x, "start column")?, number(y, "start row")?),
+            seed: u64::from_str_radix(seed, 16).map_err(|_| invalid("the seed is not a hex number"))?,
+        };
+
+        for rule in rules {
+            match rule {
+                "hex" => challenge.hex = true,
+                "wrap" => challenge.wrap = true,
+                _ if rule.len() > 1 && rule.starts_with('m') && rule[1..].bytes().all(|b| b.is_ascii_digit()) => challenge.max_mines = number(&rule[1..], "max mines")?.clamp(1, 9) as u8,
+                _ => challenge.neighborhood = Neighborhood::parse(rule).ok_or_else(|| invalid(&format!("unknown rule '{}'", rule)))?,
+            }
+        }
+
+        if challenge.width == 0 || challenge.height == 0 { return Err(invalid("the board has no cells")); }
+        if challenge.start.0 >= challenge.width || challenge.start.1 >= challenge.height { return Err(invalid("the first click is off the board")); }
+        if challenge.hex && challenge.wrap && challenge.height % 2 == 1 { return Err(invalid("a wrapped hex board needs an even number of rows")); }
+
+        Ok(challenge)
+    }
+}
+
+// the text to paste somewhere once a game is over, the challenge code lets anyone play the same board
+pub fn summary(game: &Game, label: &str, challenge: &Challenge, ascii: bool) -> String {
+    let board = game.board();
+    let time = game.elapsed();
+    let three_bv = board.three_bv();
+
+    let mut text = format!("Termsweeper {}: ", label);
+    if game.game_state == GameState::SUCSESS {
+        text.push_str(&format!("won in {}\n", stats::format_time(time)));
+        text.push_str(&format!("3BV {} at {:.2} 3BV/s\n", three_bv, three_bv as f64 / time.as_secs_f64().max(0.001)));
+    } else {
+        text.push_str(&format!("lost after {}\n", stats::format_time(time)));
+        text.push_str(&format!("3BV {}\n", three_bv));
+    }
+
+    if board.width() <= THUMBNAIL_MAX_WIDTH && board.height() <= THUMBNAIL_MAX_HEIGHT {
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                let cell = board.cell(x, y);
+                let (emoji, plain) = match cell.cell_state {
+                    CellState::REVEALED if cell.is_mine => ("💥", 'X'),
+                    CellState::REVEALED => ("⬜", '.'),
+                    CellState::FLAGGED => ("🚩", 'F'),
+                    _ if cell.is_mine => ("💣", '*'), // cells the end animation uncovered were never revealed
+                    _ => ("🟫", '#'),
+                };
+                if ascii { text.push(plain); } else { text.push_str(emoji); }
+            }
+            text.push('\n');
+        }
+    }
+
+    text.push_str(&format!("Seed {:x}, play it with: termsweeper --challenge {}\n", game.seed, challenge.encode()));
+    text
+}
+
+fn number(text: &str, what: &str) -> io::Result<usize> {
+    text.parse().map_err(|_| invalid(&format!("the {} is not a number", what)))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(neighborhood: Neighborhood) -> Challenge {
+        Challenge { width: 20, height: 12, difficulty: Difficulty::HARD, hex: false, wrap: true, neighborhood, max_mines: 3, start: (10, 6), seed: 0x3f9a0c2e71b4d586 }
+    }
+
+    #[test]
+    fn a_code_decodes_to_the_setup_it_came_from() {
+        let code = Challenge { hex: true, ..challenge(Neighborhood::Moore) }.encode();
+        assert_eq!(code, "20x12h+hex+wrap+m3-10-6-3f9a0c2e71b4d586");
+
+        let decoded = Challenge::decode(&code).unwrap();
+        assert_eq!((decoded.width, decoded.height, decoded.difficulty as usize), (20, 12, Difficulty::HARD as usize));
+        assert_eq!((decoded.hex, decoded.wrap, decoded.max_mines), (true, true, 3));
+        assert!(decoded.neighborhood == Neighborhood::Moore);
+        assert_eq!((decoded.start, decoded.seed), ((10, 6), 0x3f9a0c2e71b4d586));
+    }
+
+    #[test]
+    fn every_neighborhood_survives_the_trip() {
+        for neighborhood in [Neighborhood::Moore, Neighborhood::Knight, Neighborhood::Cross, Neighborhood::Radius2] {
+            let code = challenge(neighborhood).encode();
+            let decoded = Challenge::decode(&code).unwrap();
+            assert!(decoded.neighborhood == neighborhood, "{}", code);
+            assert_eq!(decoded.encode(), code);
+        }
+    }
+
+    #[test]
+    fn broken_codes_are_turned_away() {
+        for code in ["", "9x9e-4-4", "9x9q-4-4-1", "9e-4-4-1", "0x9e-0-0-1", "9x9e-9-4-1", "9x9e+bogus-4-4-1", "9x9e-4-4-xyz", "10x9e+hex+wrap-4-4-1"] {
+            assert!(Challenge::decode(code).is_err(), "{}", code);
+        }
+    }
+}
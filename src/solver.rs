@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+
+use crate::board::Board;
+use crate::game::CellState;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Deduction {
+    pub x: usize,
+    pub y: usize,
+    pub is_mine: bool,
+}
+
+// everything that can be proven from the revealed numbers alone as a per cell mine count, indexed like the board.
+// flags are ignored on purpose since the player may have placed them wrong
+pub fn deduce(board: &Board) -> Vec<Option<u8>> {
+    let max_mines = board.max_mines() as usize;
+    let mut known: Vec<Option<u8>> = vec![None; board.width() * board.height()];
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if board.state(x, y) != CellState::REVEALED || board.is_mine(x, y) { continue; }
+
+                let mut unknown = Vec::new();
+                let mut mines_found = 0;
+                for (nx, ny) in board.neighbors(x, y) {
+                    if board.state(nx, ny) == CellState::REVEALED { // a blown up mine is out in the open, but still a mine
+                        if board.is_mine(nx, ny) { mines_found += board.cell(nx, ny).mines as usize; }
+                        continue;
+                    }
+                    match known[board.index(nx, ny)] {
+                        Some(mines) => { mines_found += mines as usize; }
+                        None => { unknown.push(board.index(nx, ny)); }
+                    }
+                }
+
+                if unknown.is_empty() { continue; }
+
+                let mines_seen = board.mines_seen(x, y) as usize;
+                let mines_left = mines_seen - mines_found.min(mines_seen);
+                let verdict =
+                    if mines_left == 0 { Some(0) } // every mine around this number is accounted for
+                    else if mines_left == unknown.len() * max_mines { Some(max_mines as u8) } // every unknown neighbor has to be full of mines
+                    else { None };
+
+                if let Some(mines) = verdict {
+                    for i in unknown {
+                        known[i] = Some(mines);
+                    }
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    known
+}
+
+const SEARCH_LIMIT: usize = 30; // hidden cells in one group past which trying every layout takes too long
+
+// everything the revealed numbers prove when read together, found by trying every way the mines could lie
+// around them. deduce only ever looks at one number at a time, so this finds the patterns it misses. numbers
+// that share no hidden cells are worked out apart, and a group too big to search is read two numbers at a time
+pub fn prove(board: &Board) -> Vec<Option<u8>> {
+    let mut known = deduce(board);
+    if board.max_mines() > 1 { return known; } // the search below assumes one mine per cell
+
+    for group in groups(constraints(board)) {
+        let cells = group.iter().flat_map(|(_, hidden)| hidden).collect::<HashSet<_>>().len();
+        if cells <= SEARCH_LIMIT { settle(&group, &mut known); }
+        else { settle_in_pairs(&group, &mut known); }
+    }
+    known
+}
+
+// the same, but only reading two numbers at a time, which is as far as patterns like 1-2-1 need to look
+pub fn prove_in_pairs(board: &Board) -> Vec<Option<u8>> {
+    let mut known = vec![None; board.width() * board.height()];
+    settle_in_pairs(&constraints(board), &mut known);
+    known
+}
+
+fn settle_in_pairs(constraints: &[(usize, Vec<usize>)], known: &mut [Option<u8>]) {
+    for (i, first) in constraints.iter().enumerate() {
+        for second in &constraints[i + 1..] {
+            if !first.1.iter().any(|cell| second.1.contains(cell)) { continue; }
+            settle(&[first.clone(), second.clone()], known);
+        }
+    }
+}
+
+// splits the constraints into groups that share no hidden cells with each other
+fn groups(mut constraints: Vec<(usize, Vec<usize>)>) -> Vec<Vec<(usize, Vec<usize>)>> {
+    let mut groups = Vec::new();
+    while let Some(first) = constraints.pop() {
+        let mut cells: HashSet<usize> = first.1.iter().copied().collect();
+        let mut group = vec![first];
+        let mut grown = true;
+        while grown {
+            grown = false;
+            let (joining, rest): (Vec<_>, Vec<_>) = constraints.into_iter().partition(|(_, hidden)| hidden.iter().any(|cell| cells.contains(cell)));
+            constraints = rest;
+            for constraint in joining {
+                cells.extend(constraint.1.iter().copied());
+                group.push(constraint);
+                grown = true;
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+// every revealed number that touches a hidden cell, as the mines it still needs and the hidden cells it touches
+fn constraints(board: &Board) -> Vec<(usize, Vec<usize>)> {
+    let mut constraints = Vec::new();
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            if board.state(x, y) != CellState::REVEALED || board.is_mine(x, y) { continue; }
+            let hidden: Vec<usize> = board.neighbors(x, y).filter(|&(nx, ny)| board.state(nx, ny) != CellState::REVEALED).map(|(nx, ny)| board.index(nx, ny)).collect();
+            let exploded: usize = board.neighbors(x, y).filter(|&(nx, ny)| board.state(nx, ny) == CellState::REVEALED && board.is_mine(nx, ny)).map(|(nx, ny)| board.cell(nx, ny).mines as usize).sum();
+            if !hidden.is_empty() { constraints.push((board.mines_seen(x, y) as usize - exploded, hidden)); }
+        }
+    }
+    constraints
+}
+
+// marks every cell that is a mine in all the ways the constraints can be met, or in none of them
+fn settle(constraints: &[(usize, Vec<usize>)], known: &mut [Option<u8>]) {
+    let mut cells: Vec<usize> = Vec::new(); // in the order the constraints touch them, so a dead end shows early
+    for (_, hidden) in constraints {
+        for &cell in hidden {
+            if !cells.contains(&cell) { cells.push(cell); }
+        }
+    }
+
+    let mut search = Search {
+        touching: cells.iter().map(|cell| (0..constraints.len()).filter(|&i| constraints[i].1.contains(cell)).collect()).collect(),
+        needed: constraints.iter().map(|(mines, _)| *mines).collect(),
+        open: constraints.iter().map(|(_, hidden)| hidden.len()).collect(),
+        mines: vec![false; cells.len()],
+        seen_mine: vec![false; cells.len()],
+        seen_safe: vec![false; cells.len()],
+    };
+    search.place(0);
+
+    for (i, &cell) in cells.iter().enumerate() {
+        match (search.seen_mine[i], search.seen_safe[i]) {
+            (true, false) => { known[cell] = Some(1); }
+            (false, true) => { known[cell] = Some(0); }
+            _ => {}
+        }
+    }
+}
+
+struct Search {
+    touching: Vec<Vec<usize>>, // the constraints on each cell
+    needed: Vec<usize>, // mines each constraint still needs
+    open: Vec<usize>, // cells each constraint has left to decide
+    mines: Vec<bool>,
+    seen_mine: Vec<bool>,
+    seen_safe: Vec<bool>,
+}
+
+impl Search {
+    fn place(&mut self, cell: usize) {
+        if cell == self.mines.len() {
+            for (i, &mine) in self.mines.iter().enumerate() {
+                if mine { self.seen_mine[i] = true; } else { self.seen_safe[i] = true; }
+            }
+            return;
+        }
+
+        for mine in [false, true] {
+            let fits = self.touching[cell].iter().all(|&i| if mine { self.needed[i] > 0 } else { self.open[i] > self.needed[i] });
+            if !fits { continue; }
+
+            for &i in &self.touching[cell] {
+                self.open[i] -= 1;
+                if mine { self.needed[i] -= 1; }
+            }
+            self.mines[cell] = mine;
+            self.place(cell + 1);
+            for &i in &self.touching[cell] {
+                self.open[i] += 1;
+                if mine { self.needed[i] += 1; }
+            }
+        }
+    }
+}
+
+// the next move worth pointing out to the player, preferring safe reveals over flags. moves a single number
+// shows come first, reading the numbers together is only for when there are none
+pub fn hint(board: &Board) -> Option<Deduction> {
+    first_move(board, &deduce(board)).or_else(|| first_move(board, &prove(board)))
+}
+
+fn first_move(board: &Board, known: &[Option<u8>]) -> Option<Deduction> {
+    let mut flag = None;
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let state = board.state(x, y);
+            match known[board.index(x, y)] {
+                Some(0) if state == CellState::HIDDEN => { return Some(Deduction { x, y, is_mine: false }); }
+                Some(_) if state == CellState::HIDDEN && flag.is_none() => { flag = Some(Deduction { x, y, is_mine: true }); }
+                _ => {}
+            }
+        }
+    }
+
+    flag
+}
+
+// the board as text with every proven cell marked: numbers for revealed cells ('.' for an empty one, 'X' for a
+// blown up mine), '+' for a hidden cell that is proven safe, '*' for one proven to hold mines, 'F' for a flag and '#' for the rest
+pub fn report(board: &Board) -> String {
+    let known = prove(board);
+    let (mut safe, mut mines) = (0, 0);
+    let mut grid = String::new();
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let state = board.state(x, y);
+            grid.push(match known[board.index(x, y)] {
+                _ if state == CellState::REVEALED && board.is_mine(x, y) => 'X',
+                _ if state == CellState::REVEALED => match board.mines_seen(x, y) {
+                    0 => '.',
+                    seen if seen < 10 => (b'0' + seen) as char,
+                    _ => '9', // radius2 and stacked mines can count past 9
+                },
+                Some(0) => { safe += 1; '+' }
+                Some(_) => { mines += 1; '*' }
+                None if state == CellState::FLAGGED => 'F',
+                None => '#',
+            });
+        }
+        grid.push('\n');
+    }
+
+    grid.push_str(&format!("\n{} safe cell(s) and {} mine cell(s) can be proven from the revealed numbers\n", safe, mines));
+    grid
+}
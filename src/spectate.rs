@@ -0,0 +1,124 @@
+use std::{io, net::TcpListener, time::Duration};
+
+use tui::style::Color;
+
+use crate::game::{Game, GameState};
+use crate::layout::{self, Layout};
+use crate::net::{self, Peer, Session};
+use crate::topology;
+
+pub const DEFAULT_PORT: u16 = 7870; // keep in step with the --publish and --watch defaults in cli.rs
+
+// a game shown live to other terminals on the same machine. every time something on screen changes the
+// whole game goes out again, so a watcher that comes in late needs nothing else:
+//   GAME <millis> <state> <paused> <cursor x> <cursor y> <topology name>
+//   <the board as a layout file, mines included>
+//   END
+pub struct Publisher {
+    listener: TcpListener,
+    watchers: Vec<Peer>,
+    next_watcher: usize,
+    shown: Option<String>, // everything in the last snapshot but the time, which changes every frame anyway
+}
+
+impl Publisher {
+    // only this machine may watch, the snapshots carry the mines
+    pub fn new(port: u16) -> io::Result<Self> {
+        Ok(Publisher { listener: net::listen("127.0.0.1", port)?, watchers: Vec::new(), next_watcher: 0, shown: None })
+    }
+
+    pub fn port(&self) -> u16 { self.listener.local_addr().map(|address| address.port()).unwrap_or(DEFAULT_PORT) }
+}
+
+impl Session for Publisher {
+    fn poll(&mut self, game: &mut Game) {
+        let (_, gone) = net::receive_all(&mut self.watchers); // watchers never say anything, this only catches them up and finds the ones that left
+        for index in gone.into_iter().rev() { self.watchers.remove(index); }
+
+        let mut joined = net::accept(&self.listener, &mut self.next_watcher);
+        if self.watchers.is_empty() && joined.is_empty() { // nobody to show the board to, so skip building it
+            self.shown = None;
+            return;
+        }
+
+        let view = view(game);
+        let changed = self.shown.as_ref() != Some(&view);
+        let text = format!("GAME {} {}END\n", game.elapsed().as_millis(), view);
+
+        if changed { self.watchers.retain_mut(|watcher| watcher.deliver(&text).is_ok()); }
+        joined.retain_mut(|watcher| watcher.deliver(&text).is_ok());
+        self.watchers.extend(joined);
+        self.shown = Some(view);
+    }
+
+    fn panel(&self) -> Vec<(String, Color)> { Vec::new() }
+}
+
+// the game as the player sees it, mines and all so the end of the game plays out the same way
+fn view(game: &Game) -> String {
+    let state = match game.game_state {
+        GameState::ACTIVE => "active".to_string(),
+        GameState::SUCSESS => "won".to_string(),
+        GameState::FAILED => "lost".to_string(),
+        GameState::WINNER(player) => format!("winner{}", player),
+        GameState::DRAW => "draw".to_string(),
+    };
+    let (x, y) = game.cursor();
+    format!("{} {} {} {} {}\n{}", state, game.paused() as u8, x, y, game.board().topology().name(), layout::export(game.board(), true))
+}
+
+// the watching side, rebuilds the game from every snapshot that comes in
+pub struct Watcher {
+    player: Peer,
+    snapshot: Vec<String>, // the lines of the snapshot that is still coming in
+    pub player_left: bool,
+}
+
+impl Watcher {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let player = net::open(address)?;
+        player.set_nonblocking()?;
+        Ok(Watcher { player, snapshot: Vec::new(), player_left: false })
+    }
+
+    // the latest game if a new one came in, never blocks
+    pub fn poll(&mut self) -> Option<Game> {
+        let Some(lines) = self.player.receive() else {
+            self.player_left = true;
+            return None;
+        };
+
+        let mut latest = None;
+        for line in lines {
+            if line.starts_with("GAME ") { self.snapshot.clear(); }
+            if line != "END" {
+                self.snapshot.push(line);
+                continue;
+            }
+            latest = build(&self.snapshot).or(latest);
+            self.snapshot.clear();
+        }
+        latest
+    }
+}
+
+fn build(snapshot: &[String]) -> Option<Game> {
+    let header = snapshot.first()?;
+    let fields: Vec<&str> = header.splitn(7, ' ').collect();
+    let ["GAME", millis, state, paused, x, y, topology_name] = fields[..] else { return None };
+
+    let layout = Layout::parse(&snapshot[1..].join("\n")).ok()?;
+    let topology = topology::from_name(topology_name, layout.width, layout.height)?;
+    let mut game = Game::from_layout(topology, &layout).ok()?;
+
+    game.game_state = match state {
+        "active" => GameState::ACTIVE,
+        "won" => GameState::SUCSESS,
+        "lost" => GameState::FAILED,
+        "draw" => GameState::DRAW,
+        _ => GameState::WINNER(state.strip_prefix("winner")?.parse().ok()?),
+    };
+    game.set_cursor(x.parse().ok()?, y.parse().ok()?);
+    game.show_elapsed(Duration::from_millis(millis.parse().ok()?), paused == "1");
+    Some(game)
+}
@@ -0,0 +1,188 @@
+use std::{collections::BTreeMap, env, fs::{self, OpenOptions}, io::{self, Write}, path::PathBuf, time::Duration};
+
+use chrono::{Local, NaiveDate};
+
+pub const DAILY_CATEGORY: &str = "daily";
+const RECENT_DAILIES: usize = 7;
+
+// one finished game, stored one per line as tab separated fields in the history file
+pub struct Record {
+    pub category: String,
+    pub date: NaiveDate,
+    pub seed: u64,
+    pub won: bool,
+    pub time: Duration,
+    pub three_bv: usize,
+}
+
+impl Record {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.category,
+            self.date.format("%Y-%m-%d"),
+            self.seed,
+            if self.won { "won" } else { "lost" },
+            self.time.as_millis(),
+            self.three_bv,
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 6 { return None; }
+
+        Some(Record {
+            category: fields[0].to_string(),
+            date: NaiveDate::parse_from_str(fields[1], "%Y-%m-%d").ok()?,
+            seed: fields[2].parse().ok()?,
+            won: fields[3] == "won",
+            time: Duration::from_millis(fields[4].parse().ok()?),
+            three_bv: fields[5].parse().ok()?,
+        })
+    }
+}
+
+// $TERMSWEEPER_HOME if set, otherwise ~/.termsweeper
+pub fn data_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("TERMSWEEPER_HOME") { return Some(PathBuf::from(dir)); }
+    env::var("HOME").ok().map(|home| PathBuf::from(home).join(".termsweeper"))
+}
+
+fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history.txt"))
+}
+
+// a missing or unreadable history is treated as empty, lines that do not parse are skipped
+pub fn load() -> Vec<Record> {
+    let Some(path) = history_path() else { return Vec::new() };
+    fs::read_to_string(path).map(|text| text.lines().filter_map(Record::parse).collect()).unwrap_or_default()
+}
+
+pub fn append(record: &Record) -> io::Result<()> {
+    let path = history_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory to keep stats in"))?;
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record.to_line())
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// FNV-1a over the date, spelled out so the seed never changes between builds or platforms
+pub fn daily_seed(date: NaiveDate) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("termsweeper-daily-{}", date.format("%Y-%m-%d")).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn played_daily(history: &[Record], date: NaiveDate) -> bool {
+    history.iter().any(|record| record.category == DAILY_CATEGORY && record.date == date)
+}
+
+// consecutive days with a won daily, counting back from today (or yesterday if today is still unplayed)
+pub fn daily_streak(history: &[Record], today: NaiveDate) -> usize {
+    let won_on = |date: NaiveDate| history.iter().any(|record| record.category == DAILY_CATEGORY && record.date == date && record.won);
+
+    let mut day = if played_daily(history, today) { today } else { today.pred_opt().unwrap_or(today) };
+    let mut streak = 0;
+    while won_on(day) {
+        streak += 1;
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+    streak
+}
+
+pub fn daily_summary(history: &[Record], today: NaiveDate) -> String {
+    let mut text = format!("Daily streak: {} day(s)\n", daily_streak(history, today));
+
+    let dailies: Vec<&Record> = history.iter().filter(|record| record.category == DAILY_CATEGORY).collect();
+    for record in dailies.iter().rev().take(RECENT_DAILIES) {
+        text.push_str(&format!("  {}  {}\n", record.date.format("%Y-%m-%d"), describe(record)));
+    }
+
+    text
+}
+
+pub fn summary(history: &[Record]) -> String {
+    if history.is_empty() { return "No games played yet.\n".to_string(); }
+
+    let mut categories: BTreeMap<&str, Vec<&Record>> = BTreeMap::new();
+    for record in history {
+        categories.entry(record.category.as_str()).or_default().push(record);
+    }
+
+    let mut text = String::new();
+    for (category, records) in categories {
+        let won: Vec<&&Record> = records.iter().filter(|record| record.won).collect();
+        let best = won.iter().map(|record| record.time).min();
+        text.push_str(&format!(
+            "{:<14} played {:>4}  won {:>4} ({:>3}%)  best {}\n",
+            category,
+            records.len(),
+            won.len(),
+            won.len() * 100 / records.len(),
+            best.map(format_time).unwrap_or_else(|| "-".to_string()),
+        ));
+    }
+
+    text.push('\n');
+    text.push_str(&daily_summary(history, today()));
+    text
+}
+
+pub fn describe(record: &Record) -> String {
+    if record.won { format!("won in {}", format_time(record.time)) }
+    else { format!("lost after {}", format_time(record.time)) }
+}
+
+pub fn format_time(time: Duration) -> String {
+    format!("{}.{:03}s", time.as_secs(), time.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::layout;
+    use crate::preset;
+    use crate::topology::{self, Neighborhood};
+
+    // if this fails everyone's past dailies change under them, built the way play builds a daily
+    #[test]
+    fn the_daily_board_for_a_date_never_changes() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert_eq!(daily_seed(date), 0x599bdfe758a3912f);
+
+        let preset = preset::find(preset::DAILY_PRESET).unwrap();
+        let topology = topology::build(preset.width, preset.height, false, false, Neighborhood::Moore);
+        let game = Game::new(topology, preset.difficulty.clone()).with_seed(daily_seed(date)).start_at_center();
+        let exported = layout::export(game.board(), true);
+        let mines = exported.split("\n\n").next().unwrap();
+        assert_eq!(mines, "\
+..........*.*...
+............*...
+*........*.....*
+.....*....*.....
+....*...*.......
+*.........**.*..
+...**...........
+......*....*....
+.*.**........*..
+**..........*...
+.**..**....***..
+.*..............
+.**.......*.....
+......*.........
+.............*..
+*..*............");
+    }
+}
@@ -0,0 +1,75 @@
+use crate::game::Game;
+
+pub const DEFAULT_ITEMS: &str = "mines,cells,3bv,cps,preset";
+
+// one piece of the status bar under the grid, chosen on the command line with --status
+#[derive(Clone, Copy, PartialEq)]
+pub enum StatusItem {
+    Mines,           // mines left to flag
+    Cells,           // safe cells still hidden
+    ThreeBV,         // 3BV solved out of the board total
+    ClicksPerSecond, // every reveal, chord and flag over the time so far
+    Seed,
+    Preset,
+}
+
+impl StatusItem {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "mines" => Some(StatusItem::Mines),
+            "cells" => Some(StatusItem::Cells),
+            "3bv" => Some(StatusItem::ThreeBV),
+            "cps" | "clicks" => Some(StatusItem::ClicksPerSecond),
+            "seed" => Some(StatusItem::Seed),
+            "preset" => Some(StatusItem::Preset),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StatusBar {
+    pub items: Vec<StatusItem>,
+    pub preset: String, // the name stats files the game under
+}
+
+impl StatusBar {
+    // a comma separated list of items, or "none" for no bar at all. the preset is filled in once the game is set up
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let items =
+            if list.trim().eq_ignore_ascii_case("none") { Vec::new() }
+            else {
+                list.split(',')
+                    .map(|name| StatusItem::parse(name).ok_or_else(|| format!("unknown status item '{}', expected mines, cells, 3bv, cps, seed or preset", name.trim())))
+                    .collect::<Result<_, _>>()?
+            };
+        Ok(StatusBar { items, preset: String::new() })
+    }
+
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+
+    pub fn text(&self, game: &mut Game) -> String {
+        let mut parts = Vec::new();
+
+        for item in &self.items {
+            parts.push(match item {
+                StatusItem::Mines => format!("Mines {}", game.flags_available),
+                StatusItem::Cells => format!("Cells left {}", game.hidden_cells_remaining),
+                StatusItem::ThreeBV if !game.mines_placed() => "3BV -".to_string(), // the board has no mines before the first click
+                StatusItem::ThreeBV => {
+                    let (solved, total) = game.three_bv_progress();
+                    format!("3BV {}/{}", solved, total)
+                }
+                StatusItem::ClicksPerSecond => {
+                    let seconds = game.elapsed().as_secs_f64();
+                    let clicks = if seconds > 0.0 { game.moves.len() as f64 / seconds } else { 0.0 };
+                    format!("{:.2} clicks/s", clicks)
+                }
+                StatusItem::Seed => format!("Seed {:x}", game.seed),
+                StatusItem::Preset => self.preset.clone(),
+            });
+        }
+
+        parts.join(" | ")
+    }
+}
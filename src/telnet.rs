@@ -0,0 +1,100 @@
+use std::io;
+
+use crossterm::event::KeyCode;
+
+// just enough of telnet to play over it: the server takes over echoing and asks the client to send every key
+// as it is typed (suppress go ahead) and to report its window size (NAWS) whenever it changes
+const IAC: u8 = 255;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const DONT: u8 = 254;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+const MAX_PENDING: usize = 64; // far more than any command we understand, a client that leaves more unfinished is hung up on
+
+pub const NEGOTIATION: [u8; 9] = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+
+pub enum TelnetEvent {
+    Key(KeyCode),
+    Resize(u16, u16),
+}
+
+// turns the raw bytes from the client into keys, commands may be split across reads so it keeps what is left over
+#[derive(Default)]
+pub struct Telnet {
+    pending: Vec<u8>,
+    after_return: bool, // a return comes as "\r\n" or "\r\0", which may not arrive together
+}
+
+impl Telnet {
+    pub fn feed(&mut self, bytes: &[u8]) -> io::Result<Vec<TelnetEvent>> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut i = 0;
+
+        while i < self.pending.len() {
+            let rest = &self.pending[i..];
+            let after_return = std::mem::take(&mut self.after_return);
+            let (used, event) = match rest {
+                [b'\n' | 0, ..] if after_return => (1, None),
+                [IAC, IAC, ..] => (2, None), // an escaped 255, never a key worth having
+                [IAC, SB, ..] => match rest.windows(2).position(|pair| pair == [IAC, SE]) {
+                    Some(end) => (end + 2, window_size(&rest[2..end])),
+                    None => break, // the rest of the subnegotiation is still on its way
+                },
+                [IAC, command, _, ..] if (WILL..=DONT).contains(command) => (3, None), // the client agreeing, or not
+                [IAC, command] if (WILL..=DONT).contains(command) => break,
+                [IAC, _, ..] => (2, None),
+                [IAC] => break,
+
+                [0x1b, b'[' | b'O', code, ..] => (3, arrow(*code).map(TelnetEvent::Key)),
+                [0x1b, b'[' | b'O'] => break,
+                [0x1b, ..] => (1, Some(TelnetEvent::Key(KeyCode::Esc))), // a lone escape, not the start of a sequence
+
+                [b'\r', ..] => {
+                    self.after_return = true;
+                    (1, Some(TelnetEvent::Key(KeyCode::Enter)))
+                }
+                [b'\n', ..] => (1, Some(TelnetEvent::Key(KeyCode::Enter))),
+                [3, ..] => (1, Some(TelnetEvent::Key(KeyCode::Esc))), // ctrl+c leaves like escape does
+                [byte, ..] if byte.is_ascii_graphic() || *byte == b' ' => (1, Some(TelnetEvent::Key(KeyCode::Char(*byte as char)))),
+                [_, ..] => (1, None),
+                [] => break,
+            };
+            events.extend(event);
+            i += used;
+        }
+
+        self.pending.drain(..i);
+        if self.pending.len() > MAX_PENDING {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the client sent a telnet command that never ends"));
+        }
+        Ok(events)
+    }
+}
+
+fn arrow(code: u8) -> Option<KeyCode> {
+    match code {
+        b'A' => Some(KeyCode::Up),
+        b'B' => Some(KeyCode::Down),
+        b'C' => Some(KeyCode::Right),
+        b'D' => Some(KeyCode::Left),
+        _ => None,
+    }
+}
+
+// NAWS sends the width and height as two big endian numbers, with any 255 byte doubled
+fn window_size(data: &[u8]) -> Option<TelnetEvent> {
+    let [NAWS, rest @ ..] = data else { return None };
+    let mut bytes = Vec::new();
+    let mut i = 0;
+    while i < rest.len() {
+        bytes.push(rest[i]);
+        i += if rest[i] == IAC { 2 } else { 1 };
+    }
+    let [width_high, width_low, height_high, height_low] = bytes[..] else { return None };
+    Some(TelnetEvent::Resize(u16::from_be_bytes([width_high, width_low]), u16::from_be_bytes([height_high, height_low])))
+}
@@ -0,0 +1,347 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+// which cells of a square grid count as neighbors, i.e. which cells a number is counting mines in
+#[derive(Clone, Copy, PartialEq)]
+pub enum Neighborhood {
+    Moore,   // the classic 3x3 square
+    Knight,  // a chess knight's move away
+    Cross,   // only the four orthogonal cells
+    Radius2, // the 5x5 square
+}
+
+impl Neighborhood {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "moore" | "classic" => Some(Neighborhood::Moore),
+            "knight" => Some(Neighborhood::Knight),
+            "cross" => Some(Neighborhood::Cross),
+            "radius2" | "r2" => Some(Neighborhood::Radius2),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "moore",
+            Neighborhood::Knight => "knight",
+            Neighborhood::Cross => "cross",
+            Neighborhood::Radius2 => "radius2",
+        }
+    }
+
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighborhood::Moore => &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)],
+            Neighborhood::Knight => &[(-1, -2), (1, -2), (-2, -1), (2, -1), (-2, 1), (2, 1), (-1, 2), (1, 2)],
+            Neighborhood::Cross => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighborhood::Radius2 => &[
+                (-2, -2), (-1, -2), (0, -2), (1, -2), (2, -2),
+                (-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+                (-2, 0), (-1, 0), (1, 0), (2, 0),
+                (-2, 1), (-1, 1), (0, 1), (1, 1), (2, 1),
+                (-2, 2), (-1, 2), (0, 2), (1, 2), (2, 2),
+            ],
+        }
+    }
+}
+
+// how cells on the board connect to one another and where they sit on screen,
+// every neighbor lookup (through the stencil a board keeps), cursor move and grid layout goes through here
+pub trait Topology {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn stencil(&self) -> Stencil;
+    fn step(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)>;
+    fn distance(&self, a: (usize, usize), b: (usize, usize)) -> usize;
+
+    // top left corner of a cell relative to the top left corner of the grid
+    fn cell_origin(&self, x: usize, y: usize, cell_width: u16, cell_height: u16) -> (u16, u16);
+    fn grid_size(&self, cell_width: u16, cell_height: u16) -> (u16, u16);
+
+    // short description such as "square" or "hex wrap", used when a board has to be described in text.
+    // only for showing and saving, ask is_hex, wraps and neighborhood to decide anything
+    fn name(&self) -> String;
+
+    fn is_hex(&self) -> bool;
+    fn wraps(&self) -> bool;
+    fn neighborhood(&self) -> Neighborhood; // hex cells always count their six touching cells, which reads as Moore
+
+    fn cells(&self) -> usize { self.width() * self.height() }
+
+    // the plain grid every other minesweeper plays on
+    fn is_classic(&self) -> bool { !self.is_hex() && !self.wraps() && self.neighborhood() == Neighborhood::Moore }
+
+}
+
+pub struct SquareTopology {
+    width: usize,
+    height: usize,
+    wrap: bool, // when set the board edges join up, so every cell has a full set of neighbors
+    neighborhood: Neighborhood,
+}
+
+// pointy topped hexagons in "odd-r" offset rows, every odd row sits half a cell to the right
+pub struct HexTopology {
+    width: usize,
+    height: usize,
+    wrap: bool,
+}
+
+// the neighbor offsets of a whole board worked out once, the board keeps a copy so its hot loops can walk
+// neighbors without a call through the Topology trait
+#[derive(Clone, Copy)]
+pub struct Stencil {
+    offsets: [&'static [(isize, isize)]; 2], // for even and odd rows, only hex rows differ
+    width: usize,
+    height: usize,
+    wrap: bool,
+    reach: (usize, usize), // the farthest any offset goes across and down
+    dedup: bool, // narrow wrapped boards can reach the same cell (or the origin) from two directions
+}
+
+pub struct Neighbors {
+    stencil: Stencil,
+    offsets: &'static [(isize, isize)],
+    x: usize,
+    y: usize,
+    inside: bool, // far enough from every edge that no offset has to be checked, the common case
+    next: usize,
+}
+
+impl SquareTopology {
+    pub fn new(width: usize, height: usize, wrap: bool) -> Self {
+        SquareTopology { width, height, wrap, neighborhood: Neighborhood::Moore }
+    }
+
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.neighborhood = neighborhood;
+        self
+    }
+}
+
+impl Topology for SquareTopology {
+    fn width(&self) -> usize { self.width }
+    fn height(&self) -> usize { self.height }
+
+    fn stencil(&self) -> Stencil {
+        let offsets = self.neighborhood.offsets();
+        Stencil::new([offsets, offsets], self.width, self.height, self.wrap)
+    }
+
+    fn step(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        };
+        offset(self.width, self.height, self.wrap, x, y, dx, dy)
+    }
+
+    // manhattan distance, taking the shorter way around when the board wraps
+    fn distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        let dx = a.0.abs_diff(b.0);
+        let dy = a.1.abs_diff(b.1);
+
+        if self.wrap { dx.min(self.width - dx) + dy.min(self.height - dy) }
+        else { dx + dy }
+    }
+
+    fn cell_origin(&self, x: usize, y: usize, cell_width: u16, cell_height: u16) -> (u16, u16) {
+        (x as u16 * cell_width, y as u16 * cell_height)
+    }
+
+    fn grid_size(&self, cell_width: u16, cell_height: u16) -> (u16, u16) {
+        (self.width as u16 * cell_width, self.height as u16 * cell_height)
+    }
+
+    fn name(&self) -> String {
+        let mut name = "square".to_string();
+        if self.wrap { name.push_str(" wrap"); }
+        if self.neighborhood != Neighborhood::Moore { name.push(' '); name.push_str(self.neighborhood.name()); }
+        name
+    }
+
+    fn is_hex(&self) -> bool { false }
+    fn wraps(&self) -> bool { self.wrap }
+    fn neighborhood(&self) -> Neighborhood { self.neighborhood }
+}
+
+impl HexTopology {
+    pub fn new(width: usize, height: usize, wrap: bool) -> Self {
+        HexTopology { width, height, wrap }
+    }
+
+    fn cube(x: usize, y: usize) -> (isize, isize, isize) {
+        let q = x as isize - (y as isize - (y as isize & 1)) / 2;
+        let r = y as isize;
+        (q, r, -q - r)
+    }
+
+    fn direction_offset(y: usize, direction: Direction) -> Option<(isize, isize)> {
+        let odd = (y & 1) as isize; // diagonal steps depend on which way the row is shifted
+        match direction {
+            Direction::Left => Some((-1, 0)),
+            Direction::Right => Some((1, 0)),
+            Direction::UpLeft => Some((odd - 1, -1)),
+            Direction::UpRight => Some((odd, -1)),
+            Direction::DownLeft => Some((odd - 1, 1)),
+            Direction::DownRight => Some((odd, 1)),
+            _ => None,
+        }
+    }
+}
+
+impl Topology for HexTopology {
+    fn width(&self) -> usize { self.width }
+    fn height(&self) -> usize { self.height }
+
+    // left, right and the two cells above and below, which of them depends on the way the row is shifted
+    fn stencil(&self) -> Stencil {
+        let even = &[(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+        let odd = &[(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+        Stencil::new([even, odd], self.width, self.height, self.wrap)
+    }
+
+    fn step(&self, x: usize, y: usize, direction: Direction) -> Option<(usize, usize)> {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, -1), // straight up and down zig zag between the two upper or lower neighbors
+            Direction::Down => (0, 1),
+            _ => Self::direction_offset(y, direction)?,
+        };
+        offset(self.width, self.height, self.wrap, x, y, dx, dy)
+    }
+
+    fn distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        let (aq, ar, as_) = Self::cube(a.0, a.1);
+        let shifts: &[isize] = if self.wrap { &[-1, 0, 1] } else { &[0] };
+        let mut best = usize::MAX;
+
+        // when wrapping, measure against every neighboring copy of the board and keep the closest
+        for &sy in shifts {
+            for &sx in shifts {
+                let bx = b.0 as isize + sx * self.width as isize;
+                let by = b.1 as isize + sy * self.height as isize;
+                let bq = bx - (by - (by & 1)) / 2;
+                let (br, bs) = (by, -bq - by);
+                let distance = (aq - bq).unsigned_abs().max((ar - br).unsigned_abs()).max((as_ - bs).unsigned_abs());
+                best = best.min(distance);
+            }
+        }
+
+        best
+    }
+
+    fn cell_origin(&self, x: usize, y: usize, cell_width: u16, cell_height: u16) -> (u16, u16) {
+        let shift = if y % 2 == 1 { cell_width / 2 } else { 0 };
+        (x as u16 * cell_width + shift, y as u16 * cell_height)
+    }
+
+    fn grid_size(&self, cell_width: u16, cell_height: u16) -> (u16, u16) {
+        let shift = if self.height > 1 { cell_width / 2 } else { 0 };
+        (self.width as u16 * cell_width + shift, self.height as u16 * cell_height)
+    }
+
+    fn name(&self) -> String {
+        if self.wrap { "hex wrap".to_string() } else { "hex".to_string() }
+    }
+
+    fn is_hex(&self) -> bool { true }
+    fn wraps(&self) -> bool { self.wrap }
+    fn neighborhood(&self) -> Neighborhood { Neighborhood::Moore }
+}
+
+pub fn build(width: usize, height: usize, hex: bool, wrap: bool, neighborhood: Neighborhood) -> Box<dyn Topology> {
+    if hex { Box::new(HexTopology::new(width, height, wrap)) }
+    else { Box::new(SquareTopology::new(width, height, wrap).with_neighborhood(neighborhood)) }
+}
+
+// the other way round from Topology::name
+pub fn from_name(name: &str, width: usize, height: usize) -> Option<Box<dyn Topology>> {
+    let mut words = name.split_whitespace();
+    let hex = match words.next()? {
+        "hex" => true,
+        "square" => false,
+        _ => return None,
+    };
+    let (mut wrap, mut neighborhood) = (false, Neighborhood::Moore);
+    for word in words {
+        if word == "wrap" { wrap = true; }
+        else { neighborhood = Neighborhood::parse(word)?; }
+    }
+    Some(build(width, height, hex, wrap, neighborhood))
+}
+
+// the cell reached by moving (dx, dy) from (x, y), or None if that walks off an unwrapped edge
+fn offset(width: usize, height: usize, wrap: bool, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+    let nx = x as isize + dx;
+    let ny = y as isize + dy;
+
+    if wrap {
+        Some((nx.rem_euclid(width as isize) as usize, ny.rem_euclid(height as isize) as usize))
+    } else if nx >= 0 && nx < width as isize && ny >= 0 && ny < height as isize { // is neighbor real? (valid grid indexes)
+        Some((nx as usize, ny as usize))
+    } else {
+        None
+    }
+}
+
+impl Stencil {
+    fn new(offsets: [&'static [(isize, isize)]; 2], width: usize, height: usize, wrap: bool) -> Self {
+        // two offsets can only land on the same cell when the board is no wider than the distance between them
+        let reach = |axis: fn(&(isize, isize)) -> isize| offsets.iter().flat_map(|row| row.iter()).map(|o| axis(o).unsigned_abs()).max().unwrap_or(0);
+        let reach = (reach(|o| o.0), reach(|o| o.1));
+        let dedup = wrap && (width <= 2 * reach.0 || height <= 2 * reach.1);
+        Stencil { offsets, width, height, wrap, reach, dedup }
+    }
+
+    #[inline]
+    pub fn at(self, x: usize, y: usize) -> Neighbors {
+        let inside = x >= self.reach.0 && x + self.reach.0 < self.width && y >= self.reach.1 && y + self.reach.1 < self.height;
+        Neighbors { stencil: self, offsets: self.offsets[y & 1], x, y, inside, next: 0 }
+    }
+
+    fn cell(&self, x: usize, y: usize, (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+        offset(self.width, self.height, self.wrap, x, y, dx, dy)
+    }
+}
+
+impl Neighbors {
+    // the last of the offsets once the edges have had their say, kept out of next so the inside case stays small
+    #[inline(never)]
+    fn near_edge(stencil: Stencil, offsets: &[(isize, isize)], (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        let (&last, earlier) = offsets.split_last()?;
+        let cell = stencil.cell(x, y, last)?;
+        if stencil.dedup && (cell == (x, y) || earlier.iter().any(|&o| stencil.cell(x, y, o) == Some(cell))) { return None; }
+        Some(cell)
+    }
+}
+
+impl Iterator for Neighbors {
+    type Item = (usize, usize);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let i = self.next;
+            let &(dx, dy) = self.offsets.get(i)?;
+            self.next += 1;
+            if self.inside { return Some(((self.x as isize + dx) as usize, (self.y as isize + dy) as usize)); }
+            if let Some(cell) = Neighbors::near_edge(self.stencil, &self.offsets[..=i], (self.x, self.y)) { return Some(cell); }
+        }
+    }
+}
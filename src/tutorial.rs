@@ -0,0 +1,218 @@
+use tui::style::Color;
+
+use crate::game::{Action, CellState, Game};
+use crate::layout::Layout;
+use crate::solver::Deduction;
+use crate::net::Session;
+use crate::topology::{self, Neighborhood};
+
+// hand written boards that teach one pattern each. every step names the moves it wants, anything else is turned
+// away before it reaches the board, so a lesson can never be lost
+pub struct Lesson {
+    title: &'static str,
+    board: &'static str, // a board file with its state layer, see layout.rs
+    steps: &'static [Step],
+}
+
+struct Step {
+    text: &'static str,
+    moves: &'static [(Action, usize, usize)], // all of them, in any order
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Reading numbers",
+        board: BASICS,
+        steps: &[
+            Step { text: "A number counts the mines around it. The 1 at the top right touches just one hidden cell, so that cell is a mine. Flag it with f.", moves: &[(Action::Flag, 3, 0)] },
+            Step { text: "The 1 under your flag already has its mine, so the other hidden cell it touches is safe. Reveal it with Enter.", moves: &[(Action::Reveal, 2, 0)] },
+            Step { text: "The number you just opened works the same way. Reveal the next safe cell.", moves: &[(Action::Reveal, 1, 0)] },
+        ],
+    },
+    Lesson {
+        title: "Corner 1s",
+        board: CORNER,
+        steps: &[
+            Step { text: "The 1 at the left edge sees two hidden cells, the 1 next to it sees those two and one more. Their mine is in the two they share, so column 3 is safe.", moves: &[(Action::Reveal, 2, 0)] },
+            Step { text: "The same works from the right edge. Reveal column 4.", moves: &[(Action::Reveal, 3, 0)] },
+            Step { text: "Each number you opened has one hidden cell left that can hold its mine. Flag both.", moves: &[(Action::Flag, 1, 0), (Action::Flag, 4, 0)] },
+            Step { text: "The corner 1s have their mines now. Reveal the two corners.", moves: &[(Action::Reveal, 0, 0), (Action::Reveal, 5, 0)] },
+        ],
+    },
+    Lesson {
+        title: "1-2-1",
+        board: ONE_TWO_ONE,
+        steps: &[
+            Step { text: "Columns 3 to 5 read 1-2-1. A mine under the 2 would leave one of the 1s with two mines, so the 2 takes the cells under the 1s. Flag them.", moves: &[(Action::Flag, 2, 0), (Action::Flag, 4, 0)] },
+            Step { text: "Nothing is ever under the 2 of a 1-2-1. Reveal it.", moves: &[(Action::Reveal, 3, 0)] },
+            Step { text: "Both 1s have their mine, so the cells just outside the pattern are safe. Reveal them.", moves: &[(Action::Reveal, 1, 0), (Action::Reveal, 5, 0)] },
+        ],
+    },
+    Lesson {
+        title: "1-2-2-1",
+        board: ONE_TWO_TWO_ONE,
+        steps: &[
+            Step { text: "Columns 3 to 6 read 1-2-2-1. Its mines always sit under the two 2s. Flag them.", moves: &[(Action::Flag, 3, 0), (Action::Flag, 4, 0)] },
+            Step { text: "Each 1 shares its mine with a 2, so the cells under the 1s are safe.", moves: &[(Action::Reveal, 2, 0), (Action::Reveal, 5, 0)] },
+            Step { text: "So are the cells just past either end. Reveal them both.", moves: &[(Action::Reveal, 1, 0), (Action::Reveal, 6, 0)] },
+        ],
+    },
+    Lesson {
+        title: "Reductions",
+        board: REDUCTION,
+        steps: &[
+            Step { text: "Take away what you know. The 2 in column 2 sees three hidden cells, the 1 at the edge puts one mine in the two they share. The other mine is in column 3.", moves: &[(Action::Flag, 2, 0)] },
+            Step { text: "The 1 under your flag is done. Reveal the cells on either side of it.", moves: &[(Action::Reveal, 1, 0), (Action::Reveal, 3, 0)] },
+            Step { text: "The 1 in column 4 touches your flag too, so it is really a 0 for the rest. Reveal column 5.", moves: &[(Action::Reveal, 4, 0)] },
+        ],
+    },
+];
+
+pub struct Tutorial {
+    lesson: usize,
+    step: usize,
+    done: Vec<(Action, usize, usize)>, // the moves of this step made so far
+    feedback: Option<(String, Color)>,
+}
+
+impl Tutorial {
+    pub fn new(lesson: usize) -> Self {
+        Tutorial { lesson: lesson.min(LESSONS.len() - 1), step: 0, done: Vec::new(), feedback: None }
+    }
+
+    // the board the current lesson starts from
+    pub fn game(&self) -> Game {
+        let layout = Layout::parse(LESSONS[self.lesson].board).expect("the lesson boards are valid");
+        let topology = topology::build(layout.width, layout.height, false, false, Neighborhood::Moore);
+        let mut game = Game::from_layout(topology, &layout).expect("the lesson boards match their size");
+        game.message = Some("Arrows move, f flags, Enter reveals, '?' hints, 'q' leaves".to_string());
+        game
+    }
+
+    fn next_lesson(&mut self, game: &mut Game) {
+        self.lesson += 1;
+        self.step = 0;
+        if self.lesson == LESSONS.len() {
+            self.feedback = Some(("That is every lesson, well done!".to_string(), Color::Green));
+            game.message = Some("Tutorial complete, press 'q' to leave".to_string());
+            return;
+        }
+
+        self.feedback = Some((format!("Lesson done! Next up: {}", LESSONS[self.lesson].title), Color::Green));
+        let compact = game.compact;
+        *game = self.game();
+        game.compact = compact;
+    }
+}
+
+impl Session for Tutorial {
+    // a hint points at a move this step asks for, the solver may know of others the lesson is not about yet
+    fn poll(&mut self, game: &mut Game) {
+        if game.hint.is_none() { return }
+        let Some(step) = LESSONS.get(self.lesson).and_then(|lesson| lesson.steps.get(self.step)) else { return };
+        if let Some(&(action, x, y)) = step.moves.iter().find(|wanted| !self.done.contains(wanted)) {
+            game.hint = Some(Deduction { x, y, is_mine: action == Action::Flag });
+        }
+    }
+
+    fn act(&mut self, game: &mut Game, action: Action) -> bool {
+        let Some(step) = LESSONS.get(self.lesson).and_then(|lesson| lesson.steps.get(self.step)) else { return true };
+        let (x, y) = game.cursor();
+        let wanted = (action, x, y);
+
+        if !step.moves.contains(&wanted) || self.done.contains(&wanted) {
+            self.feedback = Some((mistake(game, wanted), Color::LightRed));
+            return true;
+        }
+
+        game.apply_at(x, y, action);
+        self.done.push(wanted);
+        self.feedback = Some(("Right!".to_string(), Color::Green));
+        if self.done.len() < step.moves.len() { return true; }
+
+        self.done.clear();
+        self.step += 1;
+        if self.step == LESSONS[self.lesson].steps.len() { self.next_lesson(game); }
+        true
+    }
+
+    // the lesson and what to do next, beside the board
+    fn panel(&self) -> Vec<(String, Color)> {
+        let Some(lesson) = LESSONS.get(self.lesson) else {
+            return self.feedback.iter().cloned().collect();
+        };
+
+        let mut lines = vec![
+            (format!("Lesson {} of {}", self.lesson + 1, LESSONS.len()), Color::Yellow),
+            (lesson.title.to_string(), Color::Yellow),
+            (String::new(), Color::White),
+            (lesson.steps[self.step].text.to_string(), Color::White),
+            (String::new(), Color::White),
+        ];
+        lines.extend(self.feedback.iter().cloned());
+        lines
+    }
+}
+
+// why a move was turned away, without giving the answer away
+fn mistake(game: &Game, (action, x, y): (Action, usize, usize)) -> String {
+    let board = game.board();
+    match action {
+        _ if board.state(x, y) == CellState::REVEALED => "That cell is already open".to_string(),
+        Action::Flag if board.state(x, y) == CellState::FLAGGED => "That mine is already flagged".to_string(),
+        Action::Reveal if board.is_mine(x, y) => "Careful, that cell is a mine".to_string(),
+        Action::Flag if !board.is_mine(x, y) => "That cell is safe, there is nothing to flag".to_string(),
+        _ => "True, but this step is about another cell".to_string(),
+    }
+}
+
+// each board is a hidden top row over a wall of numbers
+const BASICS: &str = "\
+*..*..
+......
+......
+
+####..
+......
+......
+";
+
+const CORNER: &str = "\
+.*..*.
+......
+......
+
+######
+......
+......
+";
+
+const ONE_TWO_ONE: &str = "\
+*.*.*.*
+.......
+.......
+
+#######
+.......
+.......
+";
+
+const ONE_TWO_TWO_ONE: &str = "\
+*..**..*
+........
+........
+
+########
+........
+........
+";
+
+const REDUCTION: &str = "\
+*.*..*
+......
+......
+
+######
+......
+......
+";